@@ -0,0 +1,298 @@
+use crate::types::{Alignment, Event, HeadingLevel, Tag};
+use std::borrow::Cow;
+
+/// Owned node of document tree, which can be built from events, edited, and turned back into events.
+///
+/// ```
+/// use markus::ast::{Node, NodeKind};
+/// use markus::html::push_html;
+/// use markus::parser::Parser;
+///
+/// let mut document = Node::from_events(Parser::new("# Title\n\ntext\n"));
+/// document.remove_child(0);
+/// document.append_child(Node::with_children(
+///     NodeKind::Paragraph,
+///     vec![Node::new(NodeKind::Text("added".to_string()))],
+/// ));
+///
+/// let mut buffer = String::new();
+/// push_html(&mut buffer, document.events());
+/// assert_eq!(buffer, "<p>text</p>\n<p>added</p>\n");
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Node {
+    pub kind: NodeKind,
+    children: Vec<Node>,
+}
+
+impl Node {
+    pub fn new(kind: NodeKind) -> Self {
+        Self::with_children(kind, Vec::new())
+    }
+
+    pub fn with_children(kind: NodeKind, children: Vec<Node>) -> Self {
+        Self { kind, children }
+    }
+
+    /// Build document node from given events, where each pair of beginning and end events becomes one node.
+    pub fn from_events<'a, I>(iterator: I) -> Self
+    where
+        I: IntoIterator<Item = Event<'a>>,
+    {
+        let mut ancestors = vec![Self::new(NodeKind::Document)];
+        for event in iterator {
+            match event {
+                Event::Begin(tag) => {
+                    ancestors.push(Self::new(NodeKind::from_tag(tag)));
+                }
+                // End events without beginning ones are ignored, not to close the document node.
+                Event::End(_) if ancestors.len() == 1 => {}
+                Event::End(_) => {
+                    let node = ancestors.pop().unwrap();
+                    ancestors.last_mut().unwrap().children.push(node);
+                }
+                event => {
+                    let node = Self::new(NodeKind::from_event(event));
+                    ancestors.last_mut().unwrap().children.push(node);
+                }
+            }
+        }
+        // Unclosed nodes are closed at the end of events.
+        while ancestors.len() > 1 {
+            let node = ancestors.pop().unwrap();
+            ancestors.last_mut().unwrap().children.push(node);
+        }
+        ancestors.pop().unwrap()
+    }
+
+    /// Return iterator of events of this node and its descendants.
+    /// `Document` node has no events on its own, and children of leaf nodes (e.g. `Text`) are ignored.
+    pub fn events(&self) -> Events<'_> {
+        Events {
+            ancestors: Vec::new(),
+            next: Some(self),
+        }
+    }
+
+    pub fn children(&self) -> &[Node] {
+        &self.children
+    }
+
+    /// Return children as mutable slice, to edit them without changing their count.
+    pub fn children_mut(&mut self) -> &mut [Node] {
+        &mut self.children
+    }
+
+    /// Add given node as the last child.
+    pub fn append_child(&mut self, child: Node) {
+        self.children.push(child);
+    }
+
+    /// Insert given node as a child at given index, shifting the following children.
+    ///
+    /// Panics if `index > len`.
+    pub fn insert_child(&mut self, index: usize, child: Node) {
+        self.children.insert(index, child);
+    }
+
+    /// Remove and return the child at given index, shifting the following children.
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn remove_child(&mut self, index: usize) -> Node {
+        self.children.remove(index)
+    }
+
+    /// Replace the child at given index with given node, and return the replaced one.
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn replace_child(&mut self, index: usize, child: Node) -> Node {
+        std::mem::replace(&mut self.children[index], child)
+    }
+
+    /// Remove all children, and return them.
+    pub fn take_children(&mut self) -> Vec<Node> {
+        std::mem::take(&mut self.children)
+    }
+}
+
+/// Kind of node, which corresponds to a tag or an event other than beginning and end.
+#[derive(Clone, Debug, PartialEq)]
+pub enum NodeKind {
+    BlockQuote,
+    /// Code block with its info string if fenced, or without it if indented.
+    CodeBlock(Option<String>),
+    Document,
+    Emphasis,
+    FootnoteDefinition(String),
+    Heading {
+        level: HeadingLevel,
+        id: Option<String>,
+        classes: Vec<String>,
+    },
+    Image {
+        dest: String,
+        title: String,
+    },
+    Item,
+    Link {
+        dest: String,
+        title: String,
+    },
+    List(Option<u64>),
+    Paragraph,
+    Strikethrough,
+    Strong,
+    Table(Vec<Alignment>),
+    TableCell,
+    TableHead,
+    TableRow,
+    Code(String),
+    FootnoteReference(String),
+    HardBreak,
+    /// One line of HTML block.
+    Html(String),
+    InlineHtml(String),
+    SoftBreak,
+    TaskListMarker(bool),
+    Text(String),
+    ThematicBreak,
+}
+
+impl NodeKind {
+    fn from_tag(tag: Tag<'_>) -> Self {
+        match tag {
+            Tag::BlockQuote => Self::BlockQuote,
+            Tag::Emphasis => Self::Emphasis,
+            Tag::FencedCodeBlock(info) => Self::CodeBlock(Some(info.into_owned())),
+            Tag::FootnoteDefinition(label) => Self::FootnoteDefinition(label.to_string()),
+            Tag::Heading { level, id, classes } => Self::Heading {
+                level,
+                id: id.map(str::to_string),
+                classes: classes.into_iter().map(str::to_string).collect(),
+            },
+            Tag::Image { dest, title } => Self::Image {
+                dest: dest.into_owned(),
+                title: title.into_owned(),
+            },
+            Tag::IndentedCodeBlock => Self::CodeBlock(None),
+            Tag::Item => Self::Item,
+            Tag::Link { dest, title } => Self::Link {
+                dest: dest.into_owned(),
+                title: title.into_owned(),
+            },
+            Tag::List(start) => Self::List(start),
+            Tag::Paragraph => Self::Paragraph,
+            Tag::Strikethrough => Self::Strikethrough,
+            Tag::Strong => Self::Strong,
+            Tag::Table(alignments) => Self::Table(alignments),
+            Tag::TableCell => Self::TableCell,
+            Tag::TableHead => Self::TableHead,
+            Tag::TableRow => Self::TableRow,
+        }
+    }
+
+    /// Convert event other than beginning and end.
+    fn from_event(event: Event<'_>) -> Self {
+        match event {
+            Event::Begin(_) | Event::End(_) => panic!("Unexpected event is found as a leaf."),
+            Event::Code(value) => Self::Code(value.into_owned()),
+            Event::FootnoteReference(label) => Self::FootnoteReference(label.to_string()),
+            Event::HardBreak => Self::HardBreak,
+            Event::Html(value) => Self::Html(value.to_string()),
+            Event::InlineHtml(value) => Self::InlineHtml(value.to_string()),
+            Event::SoftBreak => Self::SoftBreak,
+            Event::TaskListMarker(checked) => Self::TaskListMarker(checked),
+            Event::Text(value) => Self::Text(value.into_owned()),
+            Event::ThematicBreak => Self::ThematicBreak,
+        }
+    }
+
+    /// Return tag of this kind if it has beginning and end events.
+    fn tag(&self) -> Option<Tag<'_>> {
+        let tag = match self {
+            Self::BlockQuote => Tag::BlockQuote,
+            Self::CodeBlock(Some(info)) => Tag::FencedCodeBlock(Cow::Borrowed(info)),
+            Self::CodeBlock(None) => Tag::IndentedCodeBlock,
+            Self::Emphasis => Tag::Emphasis,
+            Self::FootnoteDefinition(label) => Tag::FootnoteDefinition(label),
+            Self::Heading { level, id, classes } => Tag::Heading {
+                level: *level,
+                id: id.as_deref(),
+                classes: classes.iter().map(String::as_str).collect(),
+            },
+            Self::Image { dest, title } => Tag::Image {
+                dest: Cow::Borrowed(dest),
+                title: Cow::Borrowed(title),
+            },
+            Self::Item => Tag::Item,
+            Self::Link { dest, title } => Tag::Link {
+                dest: Cow::Borrowed(dest),
+                title: Cow::Borrowed(title),
+            },
+            Self::List(start) => Tag::List(*start),
+            Self::Paragraph => Tag::Paragraph,
+            Self::Strikethrough => Tag::Strikethrough,
+            Self::Strong => Tag::Strong,
+            Self::Table(alignments) => Tag::Table(alignments.clone()),
+            Self::TableCell => Tag::TableCell,
+            Self::TableHead => Tag::TableHead,
+            Self::TableRow => Tag::TableRow,
+            _ => return None,
+        };
+        Some(tag)
+    }
+
+    /// Return event of this kind if it is a leaf.
+    fn event(&self) -> Option<Event<'_>> {
+        let event = match self {
+            Self::Code(value) => Event::Code(Cow::Borrowed(value)),
+            Self::FootnoteReference(label) => Event::FootnoteReference(label),
+            Self::HardBreak => Event::HardBreak,
+            Self::Html(value) => Event::Html(value),
+            Self::InlineHtml(value) => Event::InlineHtml(value),
+            Self::SoftBreak => Event::SoftBreak,
+            Self::TaskListMarker(checked) => Event::TaskListMarker(*checked),
+            Self::Text(value) => Event::Text(Cow::Borrowed(value)),
+            Self::ThematicBreak => Event::ThematicBreak,
+            _ => return None,
+        };
+        Some(event)
+    }
+}
+
+/// Iterator of events of node, created by `Node::events`.
+#[derive(Debug)]
+pub struct Events<'a> {
+    /// Nodes whose children are being visited, with the index of the next child.
+    ancestors: Vec<(&'a Node, usize)>,
+    next: Option<&'a Node>,
+}
+
+impl<'a> Iterator for Events<'a> {
+    type Item = Event<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(node) = self.next.take() {
+                if let Some(event) = node.kind.event() {
+                    return Some(event);
+                }
+                self.ancestors.push((node, 0));
+                if let Some(tag) = node.kind.tag() {
+                    return Some(Event::Begin(tag));
+                }
+                continue;
+            }
+            let (node, index) = self.ancestors.last_mut()?;
+            if let Some(child) = node.children.get(*index) {
+                *index += 1;
+                self.next = Some(child);
+                continue;
+            }
+            let (node, _) = self.ancestors.pop().unwrap();
+            if let Some(tag) = node.kind.tag() {
+                return Some(Event::End(tag));
+            }
+        }
+    }
+}
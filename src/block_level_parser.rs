@@ -1,119 +1,376 @@
-use crate::tree::Tree;
-use crate::types::{Block, BlockKind, HeadingLevel};
+use crate::html_scanner;
+use crate::inline_level_parser::{self, normalize_link_label};
+use crate::tree::{Tree, TreeCursor};
+use crate::types::{Alignment, Block, BlockKind, HeadingLevel, LinkReferenceDefinition, Options};
+use std::collections::HashMap;
 use std::convert::TryFrom;
+use std::ops::Range;
 
 /// Convert text into block-level tree.
 impl<'a> From<&'a str> for Tree<Block<'a>> {
     fn from(text: &'a str) -> Self {
-        Parser::new(text).run()
+        Parser::new(text, Options::empty()).run().0
     }
 }
 
-struct Parser<'a> {
+pub(crate) struct Parser<'a> {
+    code_fence: Option<CodeFence>,
+    /// Position to append blocks, whose ancestors are the open blocks.
+    cursor: TreeCursor,
+    definitions: HashMap<String, LinkReferenceDefinition<'a>>,
+    footnote_definitions: HashMap<String, &'a str>,
+    html_block_closing: Option<&'static str>,
+    last_line_blank: bool,
+    line_begin: usize,
+    options: Options,
+    partially_consumed_tab: Option<(usize, usize)>,
+    table_alignments: Vec<Alignment>,
     text: &'a str,
     tree: Tree<Block<'a>>,
 }
 
 impl<'a> Parser<'a> {
-    fn new(text: &'a str) -> Self {
+    pub(crate) fn new(text: &'a str, options: Options) -> Self {
         Self {
+            code_fence: None,
+            cursor: TreeCursor::new(),
+            definitions: HashMap::new(),
+            footnote_definitions: HashMap::new(),
+            html_block_closing: None,
+            last_line_blank: false,
+            line_begin: 0,
+            options,
+            partially_consumed_tab: None,
+            table_alignments: Vec::new(),
             text,
             tree: Tree::new(),
         }
     }
 
-    fn run(mut self) -> Tree<Block<'a>> {
+    /// Convert text into block-level tree, link reference definitions, and labels of footnote definitions.
+    pub(crate) fn run(
+        mut self,
+    ) -> (
+        Tree<Block<'a>>,
+        HashMap<String, LinkReferenceDefinition<'a>>,
+        HashMap<String, &'a str>,
+    ) {
         let mut index = 0;
         while index < self.text.len() {
-            while let Some(marker_length) = self.scan_block_quote_marker(index) {
-                index = self.parse_block_quote_marker(index, marker_length);
+            index = self.process_line(index);
+        }
+
+        self.line_begin = self.text.len();
+        self.close_unmatched_blocks(0);
+        (self.tree, self.definitions, self.footnote_definitions)
+    }
+
+    /// Process one line from given index, and return index after the line.
+    ///
+    /// As CommonMark reference implementation does, this is done in two phases.
+    /// First, the line is matched against open container blocks.
+    /// Then, new blocks are opened on the rest of the line,
+    /// or the rest is added to the innermost open block (or to the paragraph as lazy continuation line).
+    fn process_line(&mut self, begin: usize) -> usize {
+        self.line_begin = begin;
+        self.partially_consumed_tab = None;
+
+        // Phase 1: Match open blocks.
+        let (mut count, mut index) = self.parse_open_container_markers(begin);
+        let leaf = self.open_leaf_block_kind();
+        let all_matched = count == self.cursor.ancestors().len() - usize::from(leaf.is_some());
+        if all_matched {
+            match leaf {
+                Some(BlockKind::FencedCodeBlock(_)) => {
+                    return self.parse_fenced_code_block_line(index);
+                }
+                // HTML block of type 6 or 7 ends at blank line, and the other types at their closing sequence.
+                Some(BlockKind::HtmlBlock)
+                    if self.html_block_closing.is_some()
+                        || self.scan_blank_line(index).is_none() =>
+                {
+                    return self.parse_html_block_line(index);
+                }
+                Some(BlockKind::IndentedCodeBlock)
+                    if self.scan_indent(index) == 4 || self.scan_blank_line(index).is_some() =>
+                {
+                    return self.parse_indented_code_block_line(index);
+                }
+                _ => {}
             }
+        }
 
-            if let Some(length) = self.scan_blank_line(index) {
-                index += length;
+        // Phase 2: Open new container blocks.
+        let mut paragraph_open = matches!(leaf, Some(BlockKind::Paragraph));
+        let mut table_open = matches!(leaf, Some(BlockKind::Table));
+        let mut opened = false;
+        loop {
+            // List item needs some content to interrupt paragraph, and ordered list item needs to start with 1.
+            // On lazy continuation line, any list item can interrupt paragraph since it does not continue the list item.
+            let interrupting = paragraph_open && all_matched;
+            let list_marker = if self.scan_thematic_break(index).is_none() {
+                self.scan_list_marker(index).filter(|list_marker| {
+                    !interrupting
+                        || !list_marker.empty && list_marker.start.map_or(true, |start| start == 1)
+                })
             } else {
-                let indent_level = self.scan_indent(index);
-                if indent_level == 4 {
-                    index = self.parse_indented_code_block(index);
-                } else {
-                    let index_before_parse_spaces_or_tabs = index;
-                    index = self.parse_spaces_or_tabs(index);
-                    if let Some(closing) = self.scan_html_block_type_1_to_5(index) {
-                        index = self.parse_html_block_type_1_to_5(index, closing);
-                    } else if self.scan_html_block_type_6(index)
-                        || self.scan_html_block_type_7(index)
-                    {
-                        index =
-                            self.parse_html_block_type_6_to_7(index_before_parse_spaces_or_tabs);
-                    } else if let Some(length) = self.scan_thematic_break(index) {
-                        index = self.parse_thematic_break(index, length);
-                    } else if let Some(level) = self.scan_atx_heading(index) {
-                        index = self.parse_atx_heading(index, level);
-                    } else if let Some((length, byte)) = self.scan_openning_code_fence(index) {
-                        index = self.parse_fenced_code_block(index, length, byte, indent_level);
-                    } else {
-                        index = self.parse_setext_heading_or_paragraph(index);
-                    }
-                }
+                None
+            };
+            if let Some(list_marker) = list_marker {
+                self.close_unmatched_blocks(count);
+                index = self.parse_list_item(index, list_marker);
+            } else if let Some(marker_length) = self.scan_block_quote_marker(index) {
+                self.close_unmatched_blocks(count);
+                index = self.parse_block_quote_marker(index, marker_length);
+            } else if let Some((length, label)) = self.scan_footnote_definition_label(index, count)
+            {
+                self.close_unmatched_blocks(count);
+                index = self.parse_footnote_definition_label(index, length, label);
+            } else {
+                break;
             }
+            count = self.cursor.ancestors().len();
+            paragraph_open = false;
+            table_open = false;
+            opened = true;
+        }
+
+        if let Some(length) = self.scan_blank_line(index) {
+            self.close_unmatched_blocks(count);
+            // Blank line right after new container's marker, or in block quote does not separate blocks in list.
+            self.last_line_blank = !opened
+                && !self.cursor.parent().map_or(false, |node_index| {
+                    matches!(self.block(node_index).kind, BlockKind::BlockQuote)
+                });
+            return index + length;
+        }
+
+        // Indented code block cannot interrupt paragraph.
+        let indent_level = self.scan_indent(index);
+        if indent_level == 4 {
+            if paragraph_open {
+                return self.parse_paragraph_line(index);
+            }
+            self.close_unmatched_blocks(count);
+            self.open_block(index, BlockKind::IndentedCodeBlock);
+            return self.parse_indented_code_block_line(index);
+        }
+
+        let index_before_parse_spaces_or_tabs = index;
+        index = self.parse_spaces_or_tabs(index);
+        if let Some(closing) = self.scan_html_block_type_1_to_5(index) {
+            self.close_unmatched_blocks(count);
+            self.open_html_block(index_before_parse_spaces_or_tabs, Some(closing))
+        } else if self.scan_html_block_type_6(index)
+            || !paragraph_open && self.scan_html_block_type_7(index)
+        {
+            self.close_unmatched_blocks(count);
+            self.open_html_block(index_before_parse_spaces_or_tabs, None)
+        } else if let Some((length, level)) = self
+            .scan_setext_heading(index)
+            .filter(|_| paragraph_open && all_matched)
+            .filter(|_| self.parse_link_reference_definitions())
+        {
+            // Paragraph consisting only of link reference definitions cannot be setext heading.
+            self.parse_setext_heading_underline(index + length, level)
+        } else if let Some(index) = self
+            .scan_table_delimiter_row(index)
+            .filter(|_| paragraph_open && all_matched)
+            .and_then(|alignments| self.parse_table_head(index, alignments))
+        {
+            index
+        } else if let Some(length) = self.scan_thematic_break(index) {
+            self.close_unmatched_blocks(count);
+            self.parse_thematic_break(index, length)
+        } else if let Some(level) = self.scan_atx_heading(index) {
+            self.close_unmatched_blocks(count);
+            self.parse_atx_heading(index, level)
+        } else if let Some((length, byte)) = self.scan_openning_code_fence(index) {
+            self.close_unmatched_blocks(count);
+            self.open_fenced_code_block(index, length, byte, indent_level)
+        } else if paragraph_open {
+            self.parse_paragraph_line(index)
+        } else if table_open && all_matched {
+            self.parse_table_row(index)
+        } else {
+            self.close_unmatched_blocks(count);
+            let index = self.parse_task_list_marker(index);
+            self.open_block(index, BlockKind::Paragraph);
+            self.parse_paragraph_line(index)
+        }
+    }
+
+    /// Return the kind of the innermost open block if it is a leaf block accepting lines.
+    fn open_leaf_block_kind(&self) -> Option<BlockKind<'a>> {
+        let kind = self.block(self.cursor.parent()?).kind;
+        match kind {
+            BlockKind::FencedCodeBlock(_)
+            | BlockKind::HtmlBlock
+            | BlockKind::IndentedCodeBlock
+            | BlockKind::Paragraph
+            | BlockKind::Table => Some(kind),
+            _ => None,
+        }
+    }
+
+    /// Append given block to the current position.
+    fn append_block(&mut self, block: Block<'a>) {
+        self.prepare_to_append_block(&block.kind);
+        self.cursor.append(&mut self.tree, block);
+    }
+
+    /// Open given kind of block from given index, so that its children can be appended until it is closed.
+    fn open_block(&mut self, begin: usize, kind: BlockKind<'a>) {
+        self.prepare_to_append_block(&kind);
+        self.cursor.open(
+            &mut self.tree,
+            Block {
+                begin,
+                end: begin, // This will be fixed when the block is closed.
+                kind,
+            },
+        );
+    }
 
-            for _ in 0..self.tree.ancestors.len() {
-                if let Some(marker_length) = self.scan_block_quote_marker(index) {
-                    index += marker_length;
-                } else {
-                    self.tree.go_to_parent();
-                    self.tree.nodes[self.tree.current.unwrap()].item.end = index - 1;
+    /// Close the current list if given kind of block cannot be its child.
+    /// If the block follows a blank line in a list item, its list is marked as loose.
+    fn prepare_to_append_block(&mut self, kind: &BlockKind<'a>) {
+        if !matches!(kind, BlockKind::ListItem(_)) {
+            self.close_list();
+        }
+        if std::mem::take(&mut self.last_line_blank) && self.cursor.current().is_some() {
+            let list = match self.cursor.ancestors() {
+                [.., list, item] if matches!(self.block(*item).kind, BlockKind::ListItem(_)) => {
+                    Some(*list)
+                }
+                [.., list] if matches!(self.block(*list).kind, BlockKind::List { .. }) => {
+                    Some(*list)
+                }
+                _ => None,
+            };
+            if let Some(list) = list {
+                if let BlockKind::List { tight, .. } = &mut self.block_mut(list).kind {
+                    *tight = false;
                 }
             }
         }
+    }
 
-        self.tree.go_to_first();
-        self.tree
+    /// Close the innermost open block, which ends just before given index.
+    fn close_block(&mut self, index: usize) {
+        let mut has_content = true;
+        match self.block(self.cursor.parent().unwrap()).kind {
+            BlockKind::IndentedCodeBlock => {
+                self.remove_trailing_blank_lines();
+            }
+            BlockKind::Paragraph => {
+                self.remove_trailing_line_ending();
+                has_content = self.parse_link_reference_definitions();
+            }
+            _ => {}
+        }
+        let node_index = self.cursor.close().unwrap();
+        self.block_mut(node_index).end = index.saturating_sub(1);
+        if !has_content {
+            self.cursor.remove_current(&mut self.tree);
+        }
     }
 
-    fn parse_block_quote_marker(&mut self, index: usize, marker_length: usize) -> usize {
-        self.tree.append(Block {
-            begin: index,
-            end: 0, // Dummy,
-            kind: BlockKind::BlockQuote,
-        });
-        self.tree.go_to_child();
-        index + marker_length
+    /// Return the block of given node index, which is always taken from the tree.
+    fn block(&self, node_index: usize) -> &Block<'a> {
+        self.tree.item(node_index).unwrap()
     }
 
-    fn parse_html_block_type_6_to_7(&mut self, mut index: usize) -> usize {
-        while index < self.text.len() {
-            let previous_index = index;
-            index += self.scan_line(index);
-            self.tree.append(Block {
-                begin: previous_index,
-                end: index - 1,
-                kind: BlockKind::Html,
-            });
-            if self.scan_blank_line(index).is_some() {
-                break;
+    fn block_mut(&mut self, node_index: usize) -> &mut Block<'a> {
+        self.tree.item_mut(node_index).unwrap()
+    }
+
+    /// Close open blocks other than the given count of matched ones, at the beginning of the current line.
+    fn close_unmatched_blocks(&mut self, count: usize) {
+        while self.cursor.ancestors().len() > count {
+            self.close_block(self.line_begin);
+        }
+    }
+
+    /// Close the current list if it is.
+    fn close_list(&mut self) {
+        if let Some(node_index) = self.cursor.parent() {
+            if let BlockKind::List { .. } = self.block(node_index).kind {
+                self.close_block(self.line_begin);
             }
         }
-        index
     }
 
-    fn parse_html_block_type_1_to_5(&mut self, mut index: usize, closing: &str) -> usize {
-        while index < self.text.len() {
-            let previous_index = index;
-            index += self.scan_line(index);
-            self.tree.append(Block {
-                begin: previous_index,
+    fn parse_block_quote_marker(&mut self, index: usize, marker_length: usize) -> usize {
+        self.open_block(index, BlockKind::BlockQuote);
+        // Block quote marker can be followed by one optional space, or one column of tab.
+        self.parse_indent(index + marker_length, 1)
+    }
+
+    /// Open footnote definition of given label, and return index after the label and its following spaces.
+    fn parse_footnote_definition_label(
+        &mut self,
+        index: usize,
+        length: usize,
+        label: &'a str,
+    ) -> usize {
+        self.open_block(index, BlockKind::FootnoteDefinition(label));
+        self.footnote_definitions
+            .entry(normalize_link_label(label))
+            .or_insert(label);
+        self.parse_spaces_or_tabs(index + length)
+    }
+
+    /// Parse list item's marker, open its list if necessary, and return index after parse.
+    fn parse_list_item(&mut self, index: usize, list_marker: ListMarker) -> usize {
+        let continued = match self.cursor.parent() {
+            Some(node_index) => match self.block(node_index).kind {
+                BlockKind::List { marker, .. } => marker == list_marker.byte,
+                _ => false,
+            },
+            None => false,
+        };
+        if !continued {
+            self.open_block(
+                index,
+                BlockKind::List {
+                    marker: list_marker.byte,
+                    start: list_marker.start,
+                    tight: true,
+                },
+            );
+        }
+        self.open_block(index, BlockKind::ListItem(list_marker.width));
+        self.parse_indent(index + list_marker.length, list_marker.padding)
+    }
+
+    fn open_html_block(&mut self, index: usize, closing: Option<&'static str>) -> usize {
+        self.open_block(index, BlockKind::HtmlBlock);
+        self.html_block_closing = closing;
+        self.parse_html_block_line(index)
+    }
+
+    /// Parse one line of HTML block, and return index after the line.
+    fn parse_html_block_line(&mut self, begin: usize) -> usize {
+        let index = begin + self.scan_line(begin);
+        self.cursor.append(
+            &mut self.tree,
+            Block {
+                begin,
                 end: index - 1,
                 kind: BlockKind::Html,
-            });
-            if self.text[previous_index..index].contains(closing) {
-                break;
-            }
+            },
+        );
+        if self
+            .html_block_closing
+            .map_or(false, |closing| self.text[begin..index].contains(closing))
+        {
+            self.close_block(index);
         }
         index
     }
 
-    fn parse_fenced_code_block(
+    fn open_fenced_code_block(
         &mut self,
         begin: usize,
         length: usize,
@@ -125,164 +382,219 @@ impl<'a> Parser<'a> {
         index = self.parse_spaces(index);
         let line_length = self.scan_line(index);
         let info_begin = index;
-        let mut info_end = info_begin + line_length - 1; // TODO: Replace 1 with true line ending length.
-        info_end -= self.text.as_bytes()[info_begin..info_end]
-            .iter()
-            .rev()
-            .take_while(|&&b| is_non_line_ending_whitespaces(b))
-            .count();
+        // Info string ends before trailing whitespaces and line ending, which is missing at the end of text.
+        let info_end = info_begin + line_length
+            - self.text.as_bytes()[info_begin..info_begin + line_length]
+                .iter()
+                .rev()
+                .take_while(|&&b| is_whitespace(b))
+                .count();
         let info = &self.text[info_begin..info_end];
-        self.tree.append(Block {
-            begin,
-            end: 0, // Dummy,
-            kind: BlockKind::FencedCodeBlock(info),
+        self.open_block(begin, BlockKind::FencedCodeBlock(info));
+        self.code_fence = Some(CodeFence {
+            byte,
+            indent_level,
+            length,
         });
-        self.tree.go_to_child();
-
-        index += line_length;
-        loop {
-            if self.scan_container_markers(index) != self.tree.ancestors.len() - 1 {
-                break;
-            }
+        index + line_length
+    }
 
-            let index_to_check_closing = self.parse_indent(index, 3);
-            if let Some(length) = self.scan_closing_code_fence(index_to_check_closing, byte, length)
-            {
-                index = index_to_check_closing + length;
-                break;
-            }
-            index = self.parse_indent(index, indent_level);
-            index = self.parse_line(index);
+    /// Parse one line of fenced code block, and return index after the line.
+    fn parse_fenced_code_block_line(&mut self, index: usize) -> usize {
+        let code_fence = self.code_fence.unwrap();
+        let (index_to_check_closing, _) = self.scan_indent_up_to(index, 3);
+        if let Some(length) =
+            self.scan_closing_code_fence(index_to_check_closing, code_fence.byte, code_fence.length)
+        {
+            let index = index_to_check_closing + length;
+            self.close_block(index);
+            return index;
         }
+        let index = self.parse_indent(index, code_fence.indent_level);
+        self.parse_code_line(index)
+    }
 
-        self.tree.go_to_parent();
-        self.tree.nodes[self.tree.current.unwrap()].item.end = index - 1; // Fix dummy value.
-        index
+    /// Parse one line of indented code block, and return index after the line.
+    fn parse_indented_code_block_line(&mut self, index: usize) -> usize {
+        // Trailing blank lines are not part of the code block, but separate it from the next block.
+        self.last_line_blank = self.scan_blank_line(index).is_some();
+        let index = self.parse_indent(index, 4);
+        self.parse_code_line(index)
     }
 
-    /// Parse indented code block from given index, and return index after parse.
-    fn parse_indented_code_block(&mut self, mut index: usize) -> usize {
-        self.tree.append(Block {
-            begin: index,
-            end: 0, // Dummy
-            kind: BlockKind::IndentedCodeBlock,
-        });
-        self.tree.go_to_child();
+    /// Parse one line of code from given index, and return index after the line.
+    /// If the tab at the index is partially consumed as indent, its remaining columns are added as spaces.
+    fn parse_code_line(&mut self, mut index: usize) -> usize {
+        if self.consumed_tab_columns(index) > 0 {
+            let count = 4 - self.column(index) % 4;
+            self.cursor.append(
+                &mut self.tree,
+                Block {
+                    begin: index,
+                    end: index,
+                    kind: BlockKind::Spaces(count),
+                },
+            );
+            index += 1;
+        }
+        self.parse_line(index)
+    }
 
-        let mut last_non_blank_node = None;
-        let mut is_non_blank;
-        while index < self.text.len() {
-            is_non_blank = self.scan_blank_line(index).is_none();
-            index = self.parse_indent(index, 4);
-            index = self.parse_line(index);
-            if is_non_blank {
-                last_non_blank_node = self.tree.current;
-            }
-            if self.scan_container_markers(index) != self.tree.ancestors.len() - 1
-                || self.scan_indent(index) != 4 && self.scan_blank_line(index).is_none()
-            {
+    /// Remove trailing blank lines from the current indented code block.
+    fn remove_trailing_blank_lines(&mut self) {
+        while let Some(node_index) = self.cursor.current() {
+            if self.scan_blank_line(self.block(node_index).begin).is_none() {
                 break;
             }
+            self.cursor.remove_current(&mut self.tree);
         }
-        if let Some(node_index) = last_non_blank_node {
-            self.tree.nodes[node_index].next = None;
-            self.tree.current = last_non_blank_node;
+    }
+
+    /// Parse task list item marker (e.g. `[x]`) if the list item begins with it, and return index after parse.
+    fn parse_task_list_marker(&mut self, index: usize) -> usize {
+        if let Some(checked) = self.scan_task_list_marker(index) {
+            self.append_block(Block {
+                begin: index,
+                end: index + 2,
+                kind: BlockKind::TaskListMarker(checked),
+            });
+            index + 3
+        } else {
+            index
+        }
+    }
+
+    /// Parse one line of paragraph, and return index after the line.
+    fn parse_paragraph_line(&mut self, index: usize) -> usize {
+        let index = self.parse_spaces_or_tabs(index);
+        self.parse_line(index)
+    }
+
+    /// Remove line ending from the last line of the current paragraph.
+    fn remove_trailing_line_ending(&mut self) {
+        if let Some(node_index) = self.cursor.current() {
+            let item = *self.block(node_index);
+            let text = &self.text.as_bytes()[item.begin..=item.end];
+            let tail = text
+                .iter()
+                .rposition(|&byte| byte != b'\n' && byte != b'\r')
+                .map_or(0, |i| i + 1);
+            self.block_mut(node_index).end = item.begin + tail - 1;
         }
+    }
 
-        self.tree.go_to_parent();
-        self.tree.nodes[self.tree.current.unwrap()].item.end = index - 1; // Fix dummy value.
+    /// Turn the current paragraph into setext heading, and return given index after its underline.
+    fn parse_setext_heading_underline(&mut self, index: usize, level: HeadingLevel) -> usize {
+        let mut attributes = None;
+        if let Some(node_index) = self.cursor.current() {
+            let item = *self.block(node_index);
+            let text = &self.text.as_bytes()[..item.end];
+            let tail = text
+                .iter()
+                .rposition(|&byte| !is_non_line_ending_whitespaces(byte))
+                .unwrap_or(0);
+            self.block_mut(node_index).end = tail;
+            attributes = self.parse_heading_attributes();
+        }
+        self.block_mut(self.cursor.parent().unwrap()).kind = BlockKind::Heading(level, attributes);
+        self.close_block(index);
         index
     }
 
-    /// Parse setext heading or paragraph from given index, and return index after parse.
-    fn parse_setext_heading_or_paragraph(&mut self, mut index: usize) -> usize {
-        self.tree.append(Block {
-            begin: index,
-            end: 0, // This dummy value will be fixed at the end of this function.
-            kind: BlockKind::Paragraph, // Maybe paragraph, but maybe setext heading.
-        });
-        self.tree.go_to_child();
+    /// Parse link reference definitions from the beginning of current paragraph, and remove their lines.
+    /// Return true if the paragraph still has some lines.
+    fn parse_link_reference_definitions(&mut self) -> bool {
+        let paragraph = self.tree.get(self.cursor.parent().unwrap()).unwrap();
+        let lines: Vec<_> = paragraph.children().map(|line| line.index()).collect();
+        let ranges = lines
+            .iter()
+            .map(|&line| self.block(line).begin..self.block(line).end + 1)
+            .collect();
+        let (definitions, count) =
+            inline_level_parser::Parser::new(self.text, ranges, self.options)
+                .parse_link_reference_definitions();
+        for (label, definition) in definitions {
+            self.definitions.entry(label).or_insert(definition);
+        }
 
-        loop {
-            index = self.parse_spaces_or_tabs(index);
-            index = self.parse_line(index);
-
-            // Only non-lazy block quoted line can be setext heading marker.
-            let mut lazy = false;
-            let mut index2 = index;
-            for &node_index in &self.tree.ancestors {
-                if let BlockKind::BlockQuote = self.tree.nodes[node_index].item.kind {
-                    if let Some(marker_length) = self.scan_block_quote_marker(index2) {
-                        index2 += marker_length;
-                    } else {
-                        lazy = true;
-                        break;
-                    }
-                }
-            }
+        for &line in &lines[..count] {
+            self.cursor.remove(&mut self.tree, line);
+        }
+        count < lines.len()
+    }
 
-            // Skip interrupt if 4 spaces indent is detected.
-            let spaces_length = self.parse_spaces(index2) - index2;
-            if spaces_length != 4 {
-                index2 += spaces_length;
-
-                if !lazy {
-                    if let Some((length, level)) = self.scan_setext_heading(index2) {
-                        index2 += length;
-                        self.tree.nodes[*self.tree.ancestors.last().unwrap()]
-                            .item
-                            .kind = BlockKind::Heading(level);
-                        if let Some(node_index) = self.tree.current {
-                            let item = self.tree.nodes[node_index].item;
-                            let text = &self.text.as_bytes()[..item.end];
-                            let tail = text
-                                .iter()
-                                .rposition(|&byte| !is_non_line_ending_whitespaces(byte))
-                                .unwrap_or(0);
-                            self.tree.nodes[node_index].item.end = tail;
-                        }
-                        index = index2;
-                        break;
-                    }
-                }
+    /// Turn the last line of the current paragraph into table head if it has the same number of cells as given alignments,
+    /// and return index after the delimiter row at given index.
+    fn parse_table_head(&mut self, index: usize, alignments: Vec<Alignment>) -> Option<usize> {
+        let header = *self.block(self.cursor.current()?);
+        let cells = self.scan_table_row(header.begin);
+        if cells.len() != alignments.len() {
+            return None;
+        }
+        // The rest of the paragraph (if any) precedes the table.
+        self.cursor.remove_current(&mut self.tree);
+        self.close_block(header.begin);
+        self.open_block(header.begin, BlockKind::Table);
+        self.open_block(header.begin, BlockKind::TableHead);
+        for (range, &alignment) in cells.into_iter().zip(&alignments) {
+            self.parse_table_cell(range, alignment);
+        }
+        let index = index + self.scan_line(index);
+        self.close_block(index);
+        self.table_alignments = alignments;
+        Some(index)
+    }
 
-                if self.scan_paragraph_interrupt(index2) {
-                    if let Some(node_index) = self.tree.current {
-                        let item = self.tree.nodes[node_index].item;
-                        let text = &self.text.as_bytes()[item.begin..=item.end];
-                        let mut tail = text.len();
-                        tail = text[..tail]
-                            .iter()
-                            .rposition(|&byte| byte != b'\n' && byte != b'\r')
-                            .map_or(0, |i| i + 1);
-                        self.tree.nodes[node_index].item.end = item.begin + tail - 1;
-                    }
-                    break;
-                }
-            }
+    /// Parse one row of the current table, and return index after the row.
+    /// Missing cells are filled with empty ones, and excess ones are ignored.
+    fn parse_table_row(&mut self, index: usize) -> usize {
+        let mut cells = self.scan_table_row(index).into_iter();
+        let end = index + self.scan_line(index);
+        self.open_block(index, BlockKind::TableRow);
+        for alignment in self.table_alignments.clone() {
+            let range = cells.next().unwrap_or(end..end);
+            self.parse_table_cell(range, alignment);
+        }
+        self.close_block(end);
+        end
+    }
 
-            index = index2;
+    /// Parse table cell of given content range, excluding backslashes escaping pipes from its text.
+    fn parse_table_cell(&mut self, range: Range<usize>, alignment: Alignment) {
+        self.open_block(range.start, BlockKind::TableCell(alignment));
+        let mut begin = range.start;
+        for (position, _) in self.text[range.clone()].match_indices("\\|") {
+            let backslash_index = range.start + position;
+            self.append_text(begin, backslash_index);
+            begin = backslash_index + 1;
         }
+        self.append_text(begin, range.end);
+        self.close_block(range.end);
+    }
 
-        self.tree.go_to_parent();
-        self.tree.nodes[self.tree.current.unwrap()].item.end = index - 1; // Fix dummy value.
-        index
+    /// Append text of given range to the current block, unless it is empty.
+    fn append_text(&mut self, begin: usize, end: usize) {
+        if begin < end {
+            self.cursor.append(
+                &mut self.tree,
+                Block {
+                    begin,
+                    end: end - 1,
+                    kind: BlockKind::Text,
+                },
+            );
+        }
     }
 
     /// Parse ATX heading (e.g. `## Usage`) from given index, and return index after the heading.
     fn parse_atx_heading(&mut self, mut index: usize, level: HeadingLevel) -> usize {
-        self.tree.append(Block {
-            begin: index,
-            end: 0, // This dummy value will be fixed at the end of this function.
-            kind: BlockKind::Heading(level),
-        });
-        self.tree.go_to_child();
+        self.open_block(index, BlockKind::Heading(level, None));
 
         index += level as usize;
         index = self.parse_non_line_ending_whitespaces(index);
         index = self.parse_line(index);
-        if let Some(node_index) = self.tree.current {
-            let item = self.tree.nodes[node_index].item;
+        if let Some(node_index) = self.cursor.current() {
+            let item = *self.block(node_index);
             let header_text = &self.text.as_bytes()[item.begin..=item.end];
             let mut tail = header_text.len();
             tail = header_text[..tail]
@@ -308,23 +620,44 @@ impl<'a> Parser<'a> {
                     tail = tail3
                 }
             }
-            self.tree.nodes[node_index].item.end = item.begin + tail - 1;
+            self.block_mut(node_index).end = item.begin + tail - 1;
             if tail == 0 {
-                self.tree.nodes[*self.tree.ancestors.last().unwrap()].child = None;
+                self.cursor.remove_current(&mut self.tree);
+            } else if let Some(attributes) = self.parse_heading_attributes() {
+                self.block_mut(self.cursor.parent().unwrap()).kind =
+                    BlockKind::Heading(level, Some(attributes));
             }
         }
 
-        self.tree.go_to_parent();
-        self.tree.nodes[self.tree.current.unwrap()].item.end = index - 1; // Fix dummy value.
+        self.close_block(index);
         index
     }
 
+    /// Remove heading attributes (e.g. `{#id .class}`) from the end of the current heading's last line,
+    /// and return their content if found.
+    fn parse_heading_attributes(&mut self) -> Option<&'a str> {
+        if !self.options.contains(Options::ENABLE_HEADING_ATTRIBUTES) {
+            return None;
+        }
+        let node_index = self.cursor.current()?;
+        let item = *self.block(node_index);
+        let line = self.text[item.begin..=item.end].strip_suffix('}')?;
+        let position = line.rfind('{')?;
+        // Heading needs some content other than its attributes.
+        let content_length = line[..position].trim_end_matches(&[' ', '\t'][..]).len();
+        if content_length == 0 {
+            return None;
+        }
+        self.block_mut(node_index).end = item.begin + content_length - 1;
+        Some(&line[position + 1..])
+    }
+
     /// Parse thematic break, and return index after parse.
     fn parse_thematic_break(&mut self, index: usize, length: usize) -> usize {
         let end = index + length;
-        self.tree.append(Block {
+        self.append_block(Block {
             begin: index,
-            end,
+            end: end - 1,
             kind: BlockKind::ThematicBreak,
         });
         end
@@ -335,8 +668,7 @@ impl<'a> Parser<'a> {
         if index >= self.text.len() {
             return index;
         }
-        let end = if let Some(i) = self.text[index..]
-            .as_bytes()
+        let end = if let Some(i) = self.text.as_bytes()[index..]
             .iter()
             .position(|&byte| is_line_ending(byte))
         {
@@ -344,42 +676,33 @@ impl<'a> Parser<'a> {
         } else {
             self.text.len() - 1
         };
-        self.tree.append(Block {
-            begin: index,
-            end,
-            kind: BlockKind::Text,
-        });
+        self.cursor.append(
+            &mut self.tree,
+            Block {
+                begin: index,
+                end,
+                kind: BlockKind::Text,
+            },
+        );
         end + 1
     }
 
     /// Parse indent of given indent level, and return index after parse.
-    fn parse_indent(&self, index: usize, indent_level: usize) -> usize {
-        let bytes = &self.text[index..].as_bytes();
-        let mut i = 0;
-        let mut level = 0;
-        while i < bytes.len() {
-            match bytes[i] {
-                b' ' => {
-                    level += 1;
-                }
-                b'\t' => {
-                    level += 4 - i % 4;
-                }
-                _ => break,
-            }
-            if level > indent_level {
-                break;
-            }
-            i += 1;
-        }
-        index + i
+    /// If a tab is partially consumed, it is remembered so that its remaining columns can be used.
+    fn parse_indent(&mut self, index: usize, indent_level: usize) -> usize {
+        let (index, consumed_columns) = self.scan_indent_up_to(index, indent_level);
+        self.partially_consumed_tab = if consumed_columns > 0 {
+            Some((index, consumed_columns))
+        } else {
+            None
+        };
+        index
     }
 
     /// Parse 0 or more spaces or tabs, and return index after parse.
     fn parse_spaces_or_tabs(&self, index: usize) -> usize {
         index
-            + self.text[index..]
-                .as_bytes()
+            + self.text.as_bytes()[index..]
                 .iter()
                 .take_while(|&&byte| byte == b' ' || byte == b'\t')
                 .count()
@@ -388,43 +711,72 @@ impl<'a> Parser<'a> {
     /// Parse spaces, and return index after parse.
     fn parse_spaces(&self, index: usize) -> usize {
         index
-            + self.text[index..]
-                .as_bytes()
+            + self.text.as_bytes()[index..]
                 .iter()
                 .take_while(|&&byte| byte == b' ')
                 .count()
     }
 
-    fn parse_spaces_up_to(&self, index: usize, count: usize) -> usize {
-        index + std::cmp::min(self.parse_spaces(index) - index, count)
-    }
-
     /// Parse 0 or more non line ending whitespaces, and return index after parse.
     fn parse_non_line_ending_whitespaces(&self, index: usize) -> usize {
         index
-            + self.text[index..]
-                .as_bytes()
+            + self.text.as_bytes()[index..]
                 .iter()
                 .take_while(|&&byte| is_non_line_ending_whitespaces(byte))
                 .count()
     }
 
-    /// May return block quote marker byte length that starts from given byte index.
+    /// May return block quote marker byte length that starts from given byte index, excluding its following space.
     fn scan_block_quote_marker(&self, begin: usize) -> Option<usize> {
-        let mut index = self.parse_spaces_up_to(begin, 3);
+        if self.scan_indent_width(begin) >= 4 {
+            return None;
+        }
+        let index = self.parse_spaces_or_tabs(begin);
         if self.text[index..].starts_with('>') {
-            index += 1;
-            index = self.parse_spaces_up_to(index, 1);
-            Some(index - begin)
+            Some(index + 1 - begin)
         } else {
             None
         }
     }
 
+    /// Check if footnote definition's label (e.g. `[^1]:`) starts from given index, and return its length and label if found.
+    /// Footnote definition cannot be nested in another one, so it is not found in the first `count` open blocks.
+    fn scan_footnote_definition_label(
+        &self,
+        begin: usize,
+        count: usize,
+    ) -> Option<(usize, &'a str)> {
+        if !self.options.contains(Options::ENABLE_FOOTNOTES) || self.scan_indent_width(begin) >= 4 {
+            return None;
+        }
+        if self.cursor.ancestors()[..count].iter().any(|&node_index| {
+            matches!(
+                self.block(node_index).kind,
+                BlockKind::FootnoteDefinition(_)
+            )
+        }) {
+            return None;
+        }
+        let index = self.parse_spaces_or_tabs(begin);
+        if !self.text[index..].starts_with("[^") {
+            return None;
+        }
+        let label_begin = index + 2;
+        let label_length = self.text.as_bytes()[label_begin..]
+            .iter()
+            .take_while(|&&byte| byte != b']' && !is_whitespace(byte))
+            .count();
+        let label_end = label_begin + label_length;
+        if label_length == 0 || !self.text[label_end..].starts_with("]:") {
+            return None;
+        }
+        Some((label_end + 2 - begin, &self.text[label_begin..label_end]))
+    }
+
     /// Return closing sequence.
     fn scan_html_block_type_1_to_5(&self, index: usize) -> Option<&'static str> {
-        let bytes = self.text[index..].as_bytes();
-        if bytes.get(0)? != &b'<' {
+        let bytes = &self.text.as_bytes()[index..];
+        if bytes.first()? != &b'<' {
             return None;
         }
 
@@ -549,117 +901,124 @@ impl<'a> Parser<'a> {
         names.iter().any(|&element| element == name)
     }
 
+    /// Check if complete open tag or closing tag followed only by whitespaces starts from given index.
     fn scan_html_block_type_7(&self, index: usize) -> bool {
-        let mut i = index;
-        if !self.text[i..].starts_with('<') {
-            return false;
+        // The tag needs to be complete in the line.
+        let line = &self.text[..index + self.scan_line(index)];
+        html_scanner::scan_open_tag(line, index)
+            .or_else(|| html_scanner::scan_closing_tag(line, index))
+            .map_or(false, |length| {
+                self.scan_blank_line(index + length).is_some()
+            })
+    }
+
+    /// Check if table delimiter row (e.g. `| :-- | --: |`) starts from given index, and return its alignments if found.
+    fn scan_table_delimiter_row(&self, index: usize) -> Option<Vec<Alignment>> {
+        if !self.options.contains(Options::ENABLE_TABLES)
+            || !matches!(self.text.as_bytes().get(index), Some(b'|' | b':' | b'-'))
+        {
+            return None;
         }
-        i += 1;
-        let is_closing_tag = self.text[i..].starts_with('/');
-        if is_closing_tag {
-            i += 1;
+        self.scan_table_row(index)
+            .into_iter()
+            .map(|range| {
+                let cell = &self.text[range];
+                let left = cell.starts_with(':');
+                let right = cell.ends_with(':');
+                let hyphens = cell.trim_start_matches(':').trim_end_matches(':');
+                if hyphens.is_empty() || hyphens.bytes().any(|byte| byte != b'-') {
+                    return None;
+                }
+                Some(match (left, right) {
+                    (true, true) => Alignment::Center,
+                    (true, false) => Alignment::Left,
+                    (false, true) => Alignment::Right,
+                    (false, false) => Alignment::None,
+                })
+            })
+            .collect()
+    }
+
+    /// Split the line from given index into ranges of table cells, excluding their surrounding spaces and pipes.
+    /// Pipes escaped by backslashes do not split cells.
+    fn scan_table_row(&self, index: usize) -> Vec<Range<usize>> {
+        let bytes = self.text.as_bytes();
+        let mut begin = self.parse_spaces_or_tabs(index);
+        let mut end = index + self.scan_line(index);
+        end -= bytes[begin..end]
+            .iter()
+            .rev()
+            .take_while(|&&byte| is_whitespace(byte))
+            .count();
+        if bytes.get(begin) == Some(&b'|') {
+            begin += 1;
         }
-        let payload = &self.text[i..];
-        let tag_name_length = payload
-            .find(|c| !is_ascii_alphanumeric(c))
-            .unwrap_or(payload.len());
-        if tag_name_length == 0 {
-            return false;
+        if end > begin && bytes[end - 1] == b'|' && bytes[begin..end - 1].last() != Some(&b'\\') {
+            end -= 1;
         }
-        i += tag_name_length;
-
-        loop {
-            i = self.parse_non_line_ending_whitespaces(i);
-            if self.text[i..].starts_with('/') || self.text[i..].starts_with('>') {
-                break;
-            }
 
-            if let Some(attribute_length) = self.scan_attribute(i) {
-                i += attribute_length;
-            } else {
-                return false;
+        let mut cells = Vec::new();
+        let mut cell_begin = begin;
+        let mut i = begin;
+        while i < end {
+            match bytes[i] {
+                b'\\' => {
+                    i += 1;
+                }
+                b'|' => {
+                    cells.push(cell_begin..i);
+                    cell_begin = i + 1;
+                }
+                _ => {}
             }
-        }
-
-        if !is_closing_tag && self.text[i..].starts_with('/') {
             i += 1;
         }
-        self.text[i..].starts_with('>') && self.scan_blank_line(i + 1).is_some()
+        cells.push(cell_begin..end);
+        cells
+            .into_iter()
+            .map(|range| {
+                let cell = &self.text[range.clone()];
+                let leading = cell.len() - cell.trim_start_matches(&[' ', '\t'][..]).len();
+                let trailing = cell.len() - cell.trim_end_matches(&[' ', '\t'][..]).len();
+                range.start + leading..range.end - trailing.min(cell.len() - leading)
+            })
+            .collect()
     }
 
-    /// Check if HTML tag's attribute part starts from given index, and return its length if found.
-    fn scan_attribute(&self, begin: usize) -> Option<usize> {
-        let mut index = begin;
-        index += self.scan_attribute_name(index)?;
-        let spaces_length = self.parse_spaces(index) - index;
-        if self.text[index + spaces_length..].starts_with('=') {
-            index += spaces_length + 1;
-            index = self.parse_spaces(index);
-            index += self.scan_attribute_value(index)?;
-        }
-        Some(index - begin)
-    }
-
-    /// Check if HTML tag's attribute name starts from given index, and return its length if found.
-    fn scan_attribute_name(&self, index: usize) -> Option<usize> {
-        if self.text[index..].starts_with(|c| is_ascii_alpha(c) || c == '_' || c == ':') {
-            Some(
-                1 + self.text[index + 1..]
-                    .chars()
-                    .take_while(|&c| {
-                        is_ascii_alphanumeric(c) || c == '_' || c == ':' || c == '.' || c == '-'
-                    })
-                    .count(),
-            )
-        } else {
-            None
+    /// Check if task list item marker followed by spaces or tabs starts from given index at the beginning of list item,
+    /// and return whether it is checked if found.
+    fn scan_task_list_marker(&self, index: usize) -> Option<bool> {
+        if !self.options.contains(Options::ENABLE_TASKLISTS)
+            || self.cursor.current().is_some()
+            || !self.cursor.parent().map_or(false, |node_index| {
+                matches!(self.block(node_index).kind, BlockKind::ListItem(_))
+            })
+        {
+            return None;
         }
-    }
-
-    /// Check if HTML tag's attribute value starts from given index, and return its length if found.
-    fn scan_attribute_value(&self, begin: usize) -> Option<usize> {
-        let mut index = begin;
-        let bytes = &self.text.as_bytes();
-        match *bytes.get(index)? {
-            b @ b'"' | b @ b'\'' => {
-                index += 1;
-                while index < bytes.len() {
-                    if bytes[index] == b {
-                        return Some(index + 1 - begin);
-                    }
-                    if self.scan_line_ending(index).is_some() {
-                        return None;
-                    }
-                    index += 1;
-                }
-                None
-            }
-            b' ' | b'=' | b'>' | b'<' | b'`' | b'\n' | b'\r' => None,
-            _ => Some(
-                bytes
-                    .iter()
-                    .take_while(|&b| {
-                        !matches!(
-                            b,
-                            b'\'' | b'"' | b' ' | b'=' | b'>' | b'<' | b'`' | b'\n' | b'\r'
-                        )
-                    })
-                    .count(),
-            ),
+        let checked = match self.text.as_bytes().get(index..index + 4)? {
+            b"[ ] " | b"[ ]\t" => false,
+            b"[x] " | b"[x]\t" | b"[X] " | b"[X]\t" => true,
+            _ => return None,
+        };
+        // Task list item needs some content after its marker.
+        if self.scan_blank_line(index + 3).is_some() {
+            return None;
         }
+        Some(checked)
     }
 
     fn scan_repeated_byte(&self, index: usize, byte: u8) -> usize {
-        let bytes = self.text[index..].as_bytes();
+        let bytes = &self.text.as_bytes()[index..];
         bytes
             .iter()
             .position(|&byte_| byte_ != byte)
-            .unwrap_or_else(|| self.text.len())
+            .unwrap_or(bytes.len())
     }
 
     /// Check if ATX-style heading starts from given index, and return its level if found.
     fn scan_atx_heading(&self, index: usize) -> Option<HeadingLevel> {
-        let mut bytes = self.text[index..].as_bytes();
+        let mut bytes = &self.text.as_bytes()[index..];
         let position = bytes.iter().position(|&byte| byte != b' ')?;
         if position >= 4 {
             return None;
@@ -680,7 +1039,7 @@ impl<'a> Parser<'a> {
     /// Check if setext heading underline starts from given index, and returns its heading level and length if found (including line ending).
     fn scan_setext_heading(&self, index: usize) -> Option<(usize, HeadingLevel)> {
         let bytes = &self.text.as_bytes()[index..];
-        let byte = *bytes.get(0)?;
+        let byte = *bytes.first()?;
         if byte != b'=' && byte != b'-' {
             return None;
         }
@@ -726,7 +1085,7 @@ impl<'a> Parser<'a> {
                 }
                 b'\r' => {
                     i += 1;
-                    if bytes[i] == b'\n' {
+                    if bytes.get(i) == Some(&b'\n') {
                         i += 1;
                     }
                     break;
@@ -742,8 +1101,8 @@ impl<'a> Parser<'a> {
     }
 
     fn scan_openning_code_fence(&self, index: usize) -> Option<(usize, u8)> {
-        let bytes = &self.text[index..].as_bytes();
-        let byte = *bytes.get(0)?;
+        let bytes = &self.text.as_bytes()[index..];
+        let byte = *bytes.first()?;
         if byte != b'`' && byte != b'~' {
             return None;
         }
@@ -754,17 +1113,14 @@ impl<'a> Parser<'a> {
         if byte == b'~' {
             return Some((count, byte));
         }
-        if bytes[count..(count + self.scan_line(index + count))]
-            .iter()
-            .any(|&b| b == b'`')
-        {
+        if bytes[count..(count + self.scan_line(index + count))].contains(&b'`') {
             return None;
         }
         Some((count, byte))
     }
 
     fn scan_closing_code_fence(&self, index: usize, byte: u8, count: usize) -> Option<usize> {
-        let bytes = &self.text[index..].as_bytes();
+        let bytes = &self.text.as_bytes()[index..];
         if bytes.is_empty() {
             return Some(0);
         }
@@ -777,20 +1133,65 @@ impl<'a> Parser<'a> {
         Some(new_index - index)
     }
 
-    /// Check if pargraph interrupt starts from given index.
-    fn scan_paragraph_interrupt(&self, index: usize) -> bool {
-        self.scan_line_ending(index).is_some()
-            || self.scan_thematic_break(index).is_some()
-            || self.scan_atx_heading(index).is_some()
-            || self.scan_openning_code_fence(index).is_some()
-            || self.scan_html_block_type_1_to_5(index).is_some()
-            || self.scan_html_block_type_6(index)
-            || self.scan_block_quote_marker(index).is_some()
+    /// Check if list item's marker (e.g. `- `, `1. `) starts from given index, and return its information if found.
+    fn scan_list_marker(&self, begin: usize) -> Option<ListMarker> {
+        if self.scan_indent_width(begin) >= 4 {
+            return None;
+        }
+        let mut index = self.parse_spaces_or_tabs(begin);
+        let bytes = self.text.as_bytes();
+        let (byte, start) = match *bytes.get(index)? {
+            byte @ (b'-' | b'+' | b'*') => {
+                index += 1;
+                (byte, None)
+            }
+            b'0'..=b'9' => {
+                let digits_length = bytes[index..]
+                    .iter()
+                    .take_while(|byte| byte.is_ascii_digit())
+                    .count();
+                if digits_length > 9 {
+                    return None;
+                }
+                let start = self.text[index..index + digits_length].parse().ok()?;
+                index += digits_length;
+                match bytes.get(index) {
+                    Some(&byte @ (b'.' | b')')) => {
+                        index += 1;
+                        (byte, Some(start))
+                    }
+                    _ => return None,
+                }
+            }
+            _ => return None,
+        };
+
+        let empty = self.scan_blank_line(index).is_some();
+        let spaces_width = self.scan_indent_width(index);
+        if !empty && spaces_width == 0 {
+            return None;
+        }
+        // Content starting with 5 or more spaces is indented code in the list item.
+        let padding = if empty {
+            0
+        } else if spaces_width > 4 {
+            1
+        } else {
+            spaces_width
+        };
+        Some(ListMarker {
+            byte,
+            empty,
+            length: index - begin,
+            padding,
+            start,
+            width: self.column(index) - self.column(begin) + padding.max(1),
+        })
     }
 
     /// Check if line ending starts from given index, and return its length if found.
     fn scan_line_ending(&self, index: usize) -> Option<usize> {
-        let bytes = &self.text[index..].as_bytes();
+        let bytes = &self.text.as_bytes()[index..];
         if bytes.is_empty() {
             return Some(0);
         }
@@ -810,27 +1211,11 @@ impl<'a> Parser<'a> {
 
     /// Return indent level (up to 4).
     fn scan_indent(&self, index: usize) -> usize {
-        let bytes = &self.text[index..].as_bytes();
-        let mut i = 0;
-        let mut level = 0;
-        while level < 4 && i < bytes.len() {
-            match bytes[i] {
-                b' ' => {
-                    i += 1;
-                    level += 1;
-                }
-                b'\t' => {
-                    i += 1;
-                    level += 4 - i % 4;
-                }
-                _ => break,
-            }
-        }
-        level
+        std::cmp::min(self.scan_indent_width(index), 4)
     }
 
     fn scan_line(&self, index: usize) -> usize {
-        let bytes = &self.text[index..].as_bytes();
+        let bytes = &self.text.as_bytes()[index..];
         if let Some(i) = bytes.iter().position(|&byte| is_line_ending(byte)) {
             i + self.scan_line_ending(index + i).unwrap()
         } else {
@@ -838,29 +1223,139 @@ impl<'a> Parser<'a> {
         }
     }
 
-    // Scan how many container markers are located from given index, and return its count.
-    fn scan_container_markers(&self, mut index: usize) -> usize {
+    /// Parse markers of open containers continued by the line from given index,
+    /// and return their count and index after their markers.
+    fn parse_open_container_markers(&mut self, mut index: usize) -> (usize, usize) {
         let mut count = 0;
-        for &node_index in &self.tree.ancestors {
-            if let BlockKind::BlockQuote = self.tree.nodes[node_index].item.kind {
-                if let Some(marker_length) = self.scan_block_quote_marker(index) {
-                    index += marker_length;
-                    count += 1;
-                } else {
-                    break;
+        while let Some(&node_index) = self.cursor.ancestors().get(count) {
+            let node = self.tree.get(node_index).unwrap();
+            match node.item().kind {
+                BlockKind::BlockQuote => match self.scan_block_quote_marker(index) {
+                    Some(marker_length) => {
+                        index = self.parse_indent(index + marker_length, 1);
+                    }
+                    None => break,
+                },
+                // Footnote definition is continued by blank line, or line indented by 4 columns.
+                BlockKind::FootnoteDefinition(_) => {
+                    if self.scan_blank_line(index).is_none() {
+                        if self.scan_indent_width(index) < 4 {
+                            break;
+                        }
+                        index = self.parse_indent(index, 4);
+                    }
+                }
+                BlockKind::List { .. } => {}
+                BlockKind::ListItem(width) => {
+                    if self.scan_blank_line(index).is_some() {
+                        // List item can begin with at most one blank line.
+                        if node.first_child().is_none() {
+                            break;
+                        }
+                    } else if self.scan_indent_width(index) < width {
+                        break;
+                    }
+                    index = self.parse_indent(index, width);
+                }
+                _ => break,
+            }
+            count += 1;
+        }
+        (count, index)
+    }
+
+    /// Return indent width, where tab is expanded to the next tab stop.
+    fn scan_indent_width(&self, index: usize) -> usize {
+        let begin_column = self.column(index);
+        let mut column = begin_column;
+        for &byte in &self.text.as_bytes()[index..] {
+            match byte {
+                b' ' => {
+                    column += 1;
+                }
+                b'\t' => {
+                    column += 4 - column % 4;
+                }
+                _ => break,
+            }
+        }
+        column - begin_column
+    }
+
+    /// Scan indent up to given indent level,
+    /// and return index after it and columns consumed from the tab at the index (if partially consumed).
+    fn scan_indent_up_to(&self, mut index: usize, indent_level: usize) -> (usize, usize) {
+        let mut consumed_columns = self.consumed_tab_columns(index);
+        let mut column = self.column(index);
+        let end_column = column + indent_level;
+        while column < end_column {
+            match self.text.as_bytes().get(index) {
+                Some(b' ') => {
+                    column += 1;
                 }
+                Some(b'\t') => {
+                    let next_column = column + 4 - column % 4;
+                    if next_column > end_column {
+                        let tab_column = column - consumed_columns;
+                        return (index, end_column - tab_column);
+                    }
+                    column = next_column;
+                }
+                _ => break,
+            }
+            index += 1;
+            consumed_columns = 0;
+        }
+        (index, consumed_columns)
+    }
+
+    /// Return columns of the tab at given index, which are already consumed as indent or marker's following space.
+    fn consumed_tab_columns(&self, index: usize) -> usize {
+        match self.partially_consumed_tab {
+            Some((tab_index, consumed_columns)) if tab_index == index => consumed_columns,
+            _ => 0,
+        }
+    }
+
+    /// Return column of given index in the current line, where tab is expanded to the next tab stop.
+    fn column(&self, index: usize) -> usize {
+        let mut column = 0;
+        for &byte in &self.text.as_bytes()[self.line_begin..index] {
+            if byte == b'\t' {
+                column += 4 - column % 4;
+            } else {
+                column += 1;
             }
         }
-        count
+        column + self.consumed_tab_columns(index)
     }
 }
 
-fn is_ascii_alpha(c: char) -> bool {
-    matches!(c, 'a'..='z' | 'A'..='Z')
+/// Opening code fence information of fenced code block.
+#[derive(Clone, Copy)]
+struct CodeFence {
+    /// Fence byte (`` ` `` or `~`).
+    byte: u8,
+    /// Indent level of the opening code fence, which is removed from each content line.
+    indent_level: usize,
+    /// Length of the opening code fence, which closing code fence needs at least.
+    length: usize,
 }
 
-fn is_ascii_alphanumeric(c: char) -> bool {
-    is_ascii_alpha(c) || matches!(c, '0'..='9')
+/// List item's marker information.
+struct ListMarker {
+    /// Marker byte (`-`, `+`, or `*`) for bullet list, or delimiter byte (`.` or `)`) for ordered list.
+    byte: u8,
+    /// Whether the list item begins with a blank line.
+    empty: bool,
+    /// Length of the marker, excluding its following spaces.
+    length: usize,
+    /// Width of spaces following the marker, which belong to the marker.
+    padding: usize,
+    /// Start number of ordered list.
+    start: Option<u64>,
+    /// Width of the marker and following spaces, which is required as indentation of continuation lines.
+    width: usize,
 }
 
 fn is_line_ending(byte: u8) -> bool {
@@ -0,0 +1,242 @@
+use crate::types::{Event, Tag};
+use crate::unicode::is_whitespace;
+use std::borrow::Cow;
+use std::ops::Range;
+
+/// Turn extended autolinks (e.g. `www.example.com`, `https://example.com`, or `foo@example.com`) in text events into links,
+/// except for the ones already in links or images.
+pub(crate) fn parse_extended_autolinks(
+    events: Vec<(Event<'_>, Range<usize>)>,
+) -> Vec<(Event<'_>, Range<usize>)> {
+    let mut result = Vec::with_capacity(events.len());
+    let mut depth = 0;
+    let mut previous = None;
+    for (event, range) in events {
+        match event {
+            Event::Begin(Tag::Image { .. } | Tag::Link { .. }) => {
+                depth += 1;
+            }
+            Event::End(Tag::Image { .. } | Tag::Link { .. }) => {
+                depth -= 1;
+            }
+            Event::Text(value) if depth == 0 => {
+                previous = push_text_events(&mut result, value, range, previous);
+                continue;
+            }
+            _ => {}
+        }
+        previous = None;
+        result.push((event, range));
+    }
+    result
+}
+
+/// Push events of given text and its source range, where extended autolinks are split into link events,
+/// and return the last character of the text.
+fn push_text_events<'a>(
+    events: &mut Vec<(Event<'a>, Range<usize>)>,
+    value: Cow<'a, str>,
+    range: Range<usize>,
+    mut previous: Option<char>,
+) -> Option<char> {
+    // Text from character reference (e.g. `&amp;`) has different length from its source.
+    let source_range = |sub_range: Range<usize>| {
+        if value.len() == range.len() {
+            range.start + sub_range.start..range.start + sub_range.end
+        } else {
+            range.clone()
+        }
+    };
+    let mut index = 0;
+    let mut text_begin = 0;
+    while let Some(c) = value[index..].chars().next() {
+        match scan_extended_autolink(&value[index..], previous) {
+            Some((length, dest)) => {
+                let end = index + length;
+                if text_begin < index {
+                    events.push((
+                        Event::Text(slice(&value, text_begin..index)),
+                        source_range(text_begin..index),
+                    ));
+                }
+                let tag = Tag::Link {
+                    dest: dest.map_or_else(|| slice(&value, index..end), Cow::Owned),
+                    title: Cow::Borrowed(""),
+                };
+                events.push((Event::Begin(tag.clone()), source_range(index..end)));
+                events.push((
+                    Event::Text(slice(&value, index..end)),
+                    source_range(index..end),
+                ));
+                events.push((Event::End(tag), source_range(index..end)));
+                previous = value[..end].chars().next_back();
+                index = end;
+                text_begin = end;
+            }
+            None => {
+                previous = Some(c);
+                index += c.len_utf8();
+            }
+        }
+    }
+    if text_begin == 0 {
+        events.push((Event::Text(value), range));
+    } else if text_begin < value.len() {
+        let sub_range = source_range(text_begin..value.len());
+        events.push((
+            Event::Text(slice(&value, text_begin..value.len())),
+            sub_range,
+        ));
+    }
+    previous
+}
+
+/// Return given range of text, which still borrows source if the text does.
+fn slice<'a>(value: &Cow<'a, str>, range: Range<usize>) -> Cow<'a, str> {
+    match value {
+        Cow::Borrowed(value) => {
+            let value: &'a str = value;
+            Cow::Borrowed(&value[range])
+        }
+        Cow::Owned(value) => Cow::Owned(value[range].to_string()),
+    }
+}
+
+/// Check if extended autolink starts at the beginning of given text, and return its length and destination if found.
+/// The destination is omitted if it is the same as the link text.
+fn scan_extended_autolink(text: &str, previous: Option<char>) -> Option<(usize, Option<String>)> {
+    // Extended autolinks can only come at the beginning of a line, after whitespace, or after `*`, `_`, `~`, or `(`.
+    if previous.map_or(true, |c| {
+        is_whitespace(c) || matches!(c, '*' | '_' | '~' | '(')
+    }) {
+        if let Some(length) = scan_www_autolink(text) {
+            return Some((length, Some(format!("http://{}", &text[..length]))));
+        }
+        if let Some(length) = scan_url_autolink(text) {
+            return Some((length, None));
+        }
+    }
+    if previous.map_or(false, |c| c.is_ascii() && is_email_local_part_byte(c as u8)) {
+        return None;
+    }
+    scan_email_autolink(text).map(|length| (length, Some(format!("mailto:{}", &text[..length]))))
+}
+
+/// Check if extended www autolink (e.g. `www.example.com/path`) starts from the beginning, and return its length if found.
+fn scan_www_autolink(text: &str) -> Option<usize> {
+    if !text.starts_with("www.") {
+        return None;
+    }
+    let domain_length = scan_domain(text)?;
+    Some(scan_path(text, domain_length)).filter(|&length| length > 4)
+}
+
+/// Check if extended URL autolink (e.g. `https://example.com/path`) starts from the beginning, and return its length if found.
+fn scan_url_autolink(text: &str) -> Option<usize> {
+    let scheme_length = ["http://", "https://", "ftp://"]
+        .iter()
+        .find(|scheme| {
+            text.as_bytes().get(..scheme.len()).map_or(false, |prefix| {
+                prefix.eq_ignore_ascii_case(scheme.as_bytes())
+            })
+        })?
+        .len();
+    let domain_length = scan_domain(&text[scheme_length..])?;
+    Some(scan_path(text, scheme_length + domain_length)).filter(|&length| length > scheme_length)
+}
+
+/// Check if extended email autolink (e.g. `foo@example.com`) starts from the beginning, and return its length if found.
+fn scan_email_autolink(text: &str) -> Option<usize> {
+    let bytes = text.as_bytes();
+    let local_part_length = bytes
+        .iter()
+        .take_while(|&&byte| is_email_local_part_byte(byte))
+        .count();
+    if local_part_length == 0 || bytes.get(local_part_length) != Some(&b'@') {
+        return None;
+    }
+    let domain_begin = local_part_length + 1;
+    let mut end = domain_begin
+        + bytes[domain_begin..]
+            .iter()
+            .take_while(|&&byte| byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.'))
+            .count();
+    // Trailing periods are not part of the address.
+    while end > domain_begin && bytes[end - 1] == b'.' {
+        end -= 1;
+    }
+    let domain = &bytes[domain_begin..end];
+    if !domain.contains(&b'.') || matches!(domain.last(), Some(b'-' | b'_')) {
+        return None;
+    }
+    Some(end)
+}
+
+/// Check if valid domain (i.e. segments of alphanumerics, `_`, or `-`, separated by periods) starts from the beginning,
+/// and return its length if found. Underscores are not allowed in the last two segments.
+fn scan_domain(text: &str) -> Option<usize> {
+    let mut underscores = (false, false);
+    let mut length = 0;
+    for &byte in text.as_bytes() {
+        match byte {
+            b'.' => {
+                underscores = (false, underscores.0);
+            }
+            b'_' => {
+                underscores.0 = true;
+            }
+            b'-' => {}
+            _ if byte.is_ascii_alphanumeric() => {}
+            _ => break,
+        }
+        length += 1;
+    }
+    if length == 0 || underscores.0 || underscores.1 {
+        None
+    } else {
+        Some(length)
+    }
+}
+
+/// Return length of extended autolink from the beginning to its path after given index,
+/// excluding trailing punctuations, unbalanced closing parentheses, and entity-like suffix (e.g. `&amp;`).
+fn scan_path(text: &str, begin: usize) -> usize {
+    let bytes = text.as_bytes();
+    let mut end = begin
+        + bytes[begin..]
+            .iter()
+            .take_while(|&&byte| !byte.is_ascii_whitespace() && byte != b'<')
+            .count();
+    let opening = bytes[..end].iter().filter(|&&byte| byte == b'(').count();
+    let mut closing = bytes[..end].iter().filter(|&&byte| byte == b')').count();
+    while end > 0 {
+        match bytes[end - 1] {
+            b')' if closing > opening => {
+                closing -= 1;
+                end -= 1;
+            }
+            b'?' | b'!' | b'.' | b',' | b':' | b'*' | b'_' | b'~' => {
+                end -= 1;
+            }
+            b';' => {
+                let name_length = bytes[..end - 1]
+                    .iter()
+                    .rev()
+                    .take_while(|byte| byte.is_ascii_alphanumeric())
+                    .count();
+                if name_length > 0 && end - 1 > name_length && bytes[end - 2 - name_length] == b'&'
+                {
+                    end -= name_length + 2;
+                } else {
+                    end -= 1;
+                }
+            }
+            _ => break,
+        }
+    }
+    end
+}
+
+fn is_email_local_part_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || matches!(byte, b'.' | b'-' | b'_' | b'+')
+}
@@ -1,71 +1,997 @@
-use crate::types::Event;
+use crate::line_index::LineIndex;
 use crate::types::Event::*;
 use crate::types::Tag::*;
-use std::fmt::Write;
+use crate::types::{Alignment, Event, HeadingLevel, Options, Tag};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
 use std::iter::Iterator;
+use std::ops::Range;
 
 pub fn push_html<'a, I>(writer: &mut String, iterator: I)
 where
     I: Iterator<Item = Event<'a>>,
 {
-    for event in iterator {
-        match event {
-            Begin(tag) => match tag {
-                BlockQuote => {
-                    writer.push_str("<blockquote>\n");
-                }
-                FencedCodeBlock(info) => {
-                    let language = info.split(' ').next().unwrap();
-                    if language.is_empty() {
-                        writer.push_str("<pre><code>");
-                    } else {
-                        writer.push_str(&format!(
-                            r#"<pre><code class="language-{language}">"#,
-                            language = language
-                        ));
-                    }
+    push_html_ext(writer, iterator, Options::empty());
+}
+
+/// Push HTML of given events with given extensions enabled.
+pub fn push_html_ext<'a, I>(writer: &mut String, iterator: I, options: Options)
+where
+    I: Iterator<Item = Event<'a>>,
+{
+    push_html_with(writer, iterator, options, &mut DefaultHtmlRenderer);
+}
+
+/// Push HTML of given events, rendered by given renderer.
+pub fn push_html_with<'a, I, R>(
+    writer: &mut String,
+    iterator: I,
+    options: Options,
+    renderer: &mut R,
+) where
+    I: Iterator<Item = Event<'a>>,
+    R: HtmlRenderer + ?Sized,
+{
+    // Writing into String never fails.
+    HtmlWriter::new(without_offsets(iterator), None, writer, options, renderer)
+        .run()
+        .unwrap();
+}
+
+/// Push HTML of given events with their offsets in given source text (e.g. from `Parser::into_offset_iter`),
+/// which adds `data-sourcepos` attributes to block elements if `Options::ENABLE_SOURCEPOS` is enabled.
+pub fn push_html_offsets<'a, I>(writer: &mut String, text: &'a str, iterator: I, options: Options)
+where
+    I: Iterator<Item = (Event<'a>, Range<usize>)>,
+{
+    push_html_offsets_with(writer, text, iterator, options, &mut DefaultHtmlRenderer);
+}
+
+/// Push HTML of given events with their offsets in given source text, rendered by given renderer.
+pub fn push_html_offsets_with<'a, I, R>(
+    writer: &mut String,
+    text: &'a str,
+    iterator: I,
+    options: Options,
+    renderer: &mut R,
+) where
+    I: Iterator<Item = (Event<'a>, Range<usize>)>,
+    R: HtmlRenderer + ?Sized,
+{
+    // Writing into String never fails.
+    HtmlWriter::new(iterator, Some(text), writer, options, renderer)
+        .run()
+        .unwrap();
+}
+
+/// Write HTML of given events into given `io::Write` (e.g. file or socket) as it is rendered,
+/// without buffering the whole document in memory.
+///
+/// Small fragments of HTML are buffered by `io::BufWriter` internally and flushed at the end,
+/// so unbuffered writers don't need to be wrapped by callers.
+pub fn write_html<'a, I, W>(writer: W, iterator: I) -> io::Result<()>
+where
+    I: Iterator<Item = Event<'a>>,
+    W: io::Write,
+{
+    write_html_ext(writer, iterator, Options::empty())
+}
+
+/// Write HTML of given events into given `io::Write` with given extensions enabled.
+pub fn write_html_ext<'a, I, W>(writer: W, iterator: I, options: Options) -> io::Result<()>
+where
+    I: Iterator<Item = Event<'a>>,
+    W: io::Write,
+{
+    write_html_with(writer, iterator, options, &mut DefaultHtmlRenderer)
+}
+
+/// Write HTML of given events into given `io::Write`, rendered by given renderer.
+pub fn write_html_with<'a, I, W, R>(
+    writer: W,
+    iterator: I,
+    options: Options,
+    renderer: &mut R,
+) -> io::Result<()>
+where
+    I: Iterator<Item = Event<'a>>,
+    W: io::Write,
+    R: HtmlRenderer + ?Sized,
+{
+    let mut writer = IoWriter(io::BufWriter::new(writer));
+    HtmlWriter::new(
+        without_offsets(iterator),
+        None,
+        &mut writer,
+        options,
+        renderer,
+    )
+    .run()?;
+    io::Write::flush(&mut writer.0)
+}
+
+/// Write HTML of given events into given `fmt::Write` (e.g. `fmt::Formatter`).
+pub fn write_html_fmt<'a, I, W>(writer: W, iterator: I) -> fmt::Result
+where
+    I: Iterator<Item = Event<'a>>,
+    W: fmt::Write,
+{
+    write_html_fmt_ext(writer, iterator, Options::empty())
+}
+
+/// Write HTML of given events into given `fmt::Write` with given extensions enabled.
+pub fn write_html_fmt_ext<'a, I, W>(writer: W, iterator: I, options: Options) -> fmt::Result
+where
+    I: Iterator<Item = Event<'a>>,
+    W: fmt::Write,
+{
+    write_html_fmt_with(writer, iterator, options, &mut DefaultHtmlRenderer)
+}
+
+/// Write HTML of given events into given `fmt::Write`, rendered by given renderer.
+pub fn write_html_fmt_with<'a, I, W, R>(
+    writer: W,
+    iterator: I,
+    options: Options,
+    renderer: &mut R,
+) -> fmt::Result
+where
+    I: Iterator<Item = Event<'a>>,
+    W: fmt::Write,
+    R: HtmlRenderer + ?Sized,
+{
+    HtmlWriter::new(
+        without_offsets(iterator),
+        None,
+        &mut FmtWriter(writer),
+        options,
+        renderer,
+    )
+    .run()
+    .map_err(|_| fmt::Error)
+}
+
+/// Hooks to render each tag and event into HTML.
+///
+/// All hooks have default implementations that render the same HTML as `push_html`,
+/// so that implementors only need to override the ones to be customized.
+///
+/// ```
+/// use markus::html::{push_html_with, HtmlOutput, HtmlRenderer};
+/// use markus::parser::Parser;
+/// use markus::types::Options;
+/// use std::io;
+///
+/// struct Renderer;
+///
+/// impl HtmlRenderer for Renderer {
+///     fn thematic_break(&mut self, output: &mut HtmlOutput) -> io::Result<()> {
+///         output.write_line_ending_if_needed()?;
+///         output.write("<hr>\n")
+///     }
+/// }
+///
+/// let mut buffer = String::new();
+/// push_html_with(&mut buffer, Parser::new("***"), Options::empty(), &mut Renderer);
+/// assert_eq!(buffer, "<hr>\n");
+/// ```
+pub trait HtmlRenderer {
+    fn begin_block_quote(&mut self, output: &mut HtmlOutput) -> io::Result<()> {
+        output.write_line_ending_if_needed()?;
+        output.write("<blockquote")?;
+        output.write_sourcepos()?;
+        output.write(">\n")
+    }
+
+    fn end_block_quote(&mut self, output: &mut HtmlOutput) -> io::Result<()> {
+        output.write("</blockquote>\n")
+    }
+
+    fn begin_emphasis(&mut self, output: &mut HtmlOutput) -> io::Result<()> {
+        output.write("<em>")
+    }
+
+    fn end_emphasis(&mut self, output: &mut HtmlOutput) -> io::Result<()> {
+        output.write("</em>")
+    }
+
+    fn begin_fenced_code_block(&mut self, output: &mut HtmlOutput, info: &str) -> io::Result<()> {
+        output.write_line_ending_if_needed()?;
+        output.write("<pre")?;
+        output.write_sourcepos()?;
+        let language = info.split(' ').next().unwrap();
+        if language.is_empty() {
+            output.write("><code>")
+        } else {
+            output.write(r#"><code class="language-"#)?;
+            output.write_escaped_html(language)?;
+            output.write(r#"">"#)
+        }
+    }
+
+    fn end_fenced_code_block(&mut self, output: &mut HtmlOutput, _info: &str) -> io::Result<()> {
+        output.write("</code></pre>\n")
+    }
+
+    /// Called only if footnote definitions are rendered in place (i.e. without `Options::ENABLE_FOOTNOTE_SECTION`).
+    fn begin_footnote_definition(
+        &mut self,
+        output: &mut HtmlOutput,
+        label: &str,
+        number: usize,
+    ) -> io::Result<()> {
+        output.write_line_ending_if_needed()?;
+        output.write(r#"<div class="footnote-definition" id="fn-"#)?;
+        output.write_escaped_html(label)?;
+        output.write("\"")?;
+        output.write_sourcepos()?;
+        output.write(&format!(
+            r#"><sup class="footnote-definition-label">{}</sup>"#,
+            number
+        ))?;
+        output.write("\n")
+    }
+
+    /// Called only if footnote definitions are rendered in place (i.e. without `Options::ENABLE_FOOTNOTE_SECTION`).
+    fn end_footnote_definition(&mut self, output: &mut HtmlOutput, _label: &str) -> io::Result<()> {
+        output.write_line_ending_if_needed()?;
+        output.write("</div>\n")
+    }
+
+    fn begin_heading(
+        &mut self,
+        output: &mut HtmlOutput,
+        level: HeadingLevel,
+        id: Option<&str>,
+        classes: &[&str],
+    ) -> io::Result<()> {
+        output.write_line_ending_if_needed()?;
+        output.write(&format!("<h{}", level as usize))?;
+        if let Some(id) = id {
+            output.write(" id=\"")?;
+            output.write_escaped_html(id)?;
+            output.write("\"")?;
+        }
+        if !classes.is_empty() {
+            output.write(" class=\"")?;
+            output.write_escaped_html(&classes.join(" "))?;
+            output.write("\"")?;
+        }
+        output.write_sourcepos()?;
+        output.write(">")
+    }
+
+    fn end_heading(&mut self, output: &mut HtmlOutput, level: HeadingLevel) -> io::Result<()> {
+        output.write(&format!("</h{}>\n", level as usize))
+    }
+
+    /// Render the whole image, where `alt` is the text of its description without any tags.
+    fn image(
+        &mut self,
+        output: &mut HtmlOutput,
+        dest: &str,
+        title: &str,
+        alt: &str,
+    ) -> io::Result<()> {
+        output.write("<img src=\"")?;
+        output.write_escaped_href(dest)?;
+        output.write("\" alt=\"")?;
+        output.write_escaped_html(alt)?;
+        output.write("\"")?;
+        if !title.is_empty() {
+            output.write(" title=\"")?;
+            output.write_escaped_html(title)?;
+            output.write("\"")?;
+        }
+        output.write(" />")
+    }
+
+    fn begin_indented_code_block(&mut self, output: &mut HtmlOutput) -> io::Result<()> {
+        output.write_line_ending_if_needed()?;
+        output.write("<pre")?;
+        output.write_sourcepos()?;
+        output.write("><code>")
+    }
+
+    fn end_indented_code_block(&mut self, output: &mut HtmlOutput) -> io::Result<()> {
+        output.write("</code></pre>\n")
+    }
+
+    fn begin_item(&mut self, output: &mut HtmlOutput) -> io::Result<()> {
+        output.write_line_ending_if_needed()?;
+        output.write("<li")?;
+        output.write_sourcepos()?;
+        output.write(">")
+    }
+
+    fn end_item(&mut self, output: &mut HtmlOutput) -> io::Result<()> {
+        output.write("</li>\n")
+    }
+
+    fn begin_link(&mut self, output: &mut HtmlOutput, dest: &str, title: &str) -> io::Result<()> {
+        output.write("<a href=\"")?;
+        output.write_escaped_href(dest)?;
+        if !title.is_empty() {
+            output.write("\" title=\"")?;
+            output.write_escaped_html(title)?;
+        }
+        output.write("\">")
+    }
+
+    fn end_link(&mut self, output: &mut HtmlOutput, _dest: &str, _title: &str) -> io::Result<()> {
+        output.write("</a>")
+    }
+
+    /// Begin ordered list if `start` is given, or bullet list otherwise.
+    fn begin_list(&mut self, output: &mut HtmlOutput, start: Option<u64>) -> io::Result<()> {
+        output.write_line_ending_if_needed()?;
+        match start {
+            Some(1) => output.write("<ol")?,
+            Some(start) => output.write(&format!("<ol start=\"{}\"", start))?,
+            None => output.write("<ul")?,
+        }
+        output.write_sourcepos()?;
+        output.write(">\n")
+    }
+
+    fn end_list(&mut self, output: &mut HtmlOutput, start: Option<u64>) -> io::Result<()> {
+        output.write_line_ending_if_needed()?;
+        match start {
+            Some(_) => output.write("</ol>\n"),
+            None => output.write("</ul>\n"),
+        }
+    }
+
+    fn begin_paragraph(&mut self, output: &mut HtmlOutput) -> io::Result<()> {
+        output.write_line_ending_if_needed()?;
+        output.write("<p")?;
+        output.write_sourcepos()?;
+        output.write(">")
+    }
+
+    fn end_paragraph(&mut self, output: &mut HtmlOutput) -> io::Result<()> {
+        output.write("</p>\n")
+    }
+
+    fn begin_strikethrough(&mut self, output: &mut HtmlOutput) -> io::Result<()> {
+        output.write("<del>")
+    }
+
+    fn end_strikethrough(&mut self, output: &mut HtmlOutput) -> io::Result<()> {
+        output.write("</del>")
+    }
+
+    fn begin_strong(&mut self, output: &mut HtmlOutput) -> io::Result<()> {
+        output.write("<strong>")
+    }
+
+    fn end_strong(&mut self, output: &mut HtmlOutput) -> io::Result<()> {
+        output.write("</strong>")
+    }
+
+    fn begin_table(
+        &mut self,
+        output: &mut HtmlOutput,
+        _alignments: &[Alignment],
+    ) -> io::Result<()> {
+        output.write_line_ending_if_needed()?;
+        output.write("<table")?;
+        output.write_sourcepos()?;
+        output.write(">\n")
+    }
+
+    fn end_table(&mut self, output: &mut HtmlOutput) -> io::Result<()> {
+        output.write("</table>\n")
+    }
+
+    /// Called before the first row after the head row, which is not an event on its own.
+    fn begin_table_body(&mut self, output: &mut HtmlOutput) -> io::Result<()> {
+        output.write("<tbody>\n")
+    }
+
+    /// Called at the end of table only if the table has any body row.
+    fn end_table_body(&mut self, output: &mut HtmlOutput) -> io::Result<()> {
+        output.write("</tbody>\n")
+    }
+
+    /// Begin cell with its column's alignment, where `head` tells if the cell is in the head row.
+    fn begin_table_cell(
+        &mut self,
+        output: &mut HtmlOutput,
+        alignment: Alignment,
+        head: bool,
+    ) -> io::Result<()> {
+        output.write(if head { "<th" } else { "<td" })?;
+        match alignment {
+            Alignment::Center => output.write(r#" align="center""#)?,
+            Alignment::Left => output.write(r#" align="left""#)?,
+            Alignment::Right => output.write(r#" align="right""#)?,
+            Alignment::None => {}
+        }
+        output.write_sourcepos()?;
+        output.write(">")
+    }
+
+    fn end_table_cell(&mut self, output: &mut HtmlOutput, head: bool) -> io::Result<()> {
+        output.write(if head { "</th>\n" } else { "</td>\n" })
+    }
+
+    fn begin_table_head(&mut self, output: &mut HtmlOutput) -> io::Result<()> {
+        output.write("<thead>\n<tr")?;
+        output.write_sourcepos()?;
+        output.write(">\n")
+    }
+
+    fn end_table_head(&mut self, output: &mut HtmlOutput) -> io::Result<()> {
+        output.write("</tr>\n</thead>\n")
+    }
+
+    fn begin_table_row(&mut self, output: &mut HtmlOutput) -> io::Result<()> {
+        output.write("<tr")?;
+        output.write_sourcepos()?;
+        output.write(">\n")
+    }
+
+    fn end_table_row(&mut self, output: &mut HtmlOutput) -> io::Result<()> {
+        output.write("</tr>\n")
+    }
+
+    fn code(&mut self, output: &mut HtmlOutput, value: &str) -> io::Result<()> {
+        output.write("<code>")?;
+        output.write_escaped_html(value)?;
+        output.write("</code>")
+    }
+
+    /// Render reference to footnote, where `count` is how many times the footnote has been referenced so far.
+    fn footnote_reference(
+        &mut self,
+        output: &mut HtmlOutput,
+        label: &str,
+        number: usize,
+        count: usize,
+    ) -> io::Result<()> {
+        output.write(r##"<sup class="footnote-ref"><a href="#fn-"##)?;
+        output.write_escaped_html(label)?;
+        output.write(r#"" id=""#)?;
+        write_footnote_reference_id(output, label, count)?;
+        output.write(&format!(r#"">{}</a></sup>"#, number))
+    }
+
+    fn hard_break(&mut self, output: &mut HtmlOutput) -> io::Result<()> {
+        output.write("<br />\n")
+    }
+
+    fn html(&mut self, output: &mut HtmlOutput, value: &str) -> io::Result<()> {
+        output.write_line_ending_if_needed()?;
+        output.write_raw_html(value)
+    }
+
+    fn inline_html(&mut self, output: &mut HtmlOutput, value: &str) -> io::Result<()> {
+        output.write_raw_html(value)
+    }
+
+    fn soft_break(&mut self, output: &mut HtmlOutput) -> io::Result<()> {
+        output.write("\n")
+    }
+
+    fn task_list_marker(&mut self, output: &mut HtmlOutput, checked: bool) -> io::Result<()> {
+        if checked {
+            output.write(r#"<input type="checkbox" disabled="" checked="" /> "#)
+        } else {
+            output.write(r#"<input type="checkbox" disabled="" /> "#)
+        }
+    }
+
+    fn text(&mut self, output: &mut HtmlOutput, value: &str) -> io::Result<()> {
+        output.write_escaped_html(value)
+    }
+
+    fn thematic_break(&mut self, output: &mut HtmlOutput) -> io::Result<()> {
+        output.write_line_ending_if_needed()?;
+        output.write("<hr")?;
+        output.write_sourcepos()?;
+        output.write(" />\n")
+    }
+}
+
+/// Renderer without any customization.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DefaultHtmlRenderer;
+
+impl HtmlRenderer for DefaultHtmlRenderer {}
+
+/// Destination of HTML given to `HtmlRenderer`, which keeps track of the last written text.
+pub struct HtmlOutput<'w> {
+    writer: &'w mut dyn StrWrite,
+    /// Whether nothing is written yet or the last written text ends with line ending.
+    end_newline: bool,
+    tagfilter: bool,
+    /// Buffer of the footnote definition being collected, with `end_newline` outside of it.
+    footnote_buffer: Option<(String, bool)>,
+    /// Line and column numbers of the beginning and end of the current event, if `data-sourcepos` is enabled.
+    sourcepos: Option<((usize, usize), (usize, usize))>,
+}
+
+impl<'w> HtmlOutput<'w> {
+    fn new(writer: &'w mut dyn StrWrite, options: Options) -> Self {
+        Self {
+            writer,
+            end_newline: true,
+            tagfilter: options.contains(Options::ENABLE_TAGFILTER),
+            footnote_buffer: None,
+            sourcepos: None,
+        }
+    }
+
+    /// Write `data-sourcepos` attribute of the current event (e.g. ` data-sourcepos="1:1-2:5"`),
+    /// only if `Options::ENABLE_SOURCEPOS` is enabled and the events are given with their offsets.
+    pub fn write_sourcepos(&mut self) -> io::Result<()> {
+        match self.sourcepos {
+            Some(((begin_line, begin_column), (end_line, end_column))) => self.write(&format!(
+                r#" data-sourcepos="{}:{}-{}:{}""#,
+                begin_line, begin_column, end_line, end_column
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Write given text as it is.
+    pub fn write(&mut self, value: &str) -> io::Result<()> {
+        if value.is_empty() {
+            return Ok(());
+        }
+        self.end_newline = value.ends_with('\n');
+        match &mut self.footnote_buffer {
+            Some((buffer, _)) => {
+                buffer.push_str(value);
+                Ok(())
+            }
+            None => self.writer.write_str(value),
+        }
+    }
+
+    /// Write line ending if not at the beginning of a line, so that block-level tag starts on a new line.
+    pub fn write_line_ending_if_needed(&mut self) -> io::Result<()> {
+        if self.end_newline {
+            Ok(())
+        } else {
+            self.write("\n")
+        }
+    }
+
+    /// Write given raw HTML as it is, or with escaping the leading `<` of disallowed tags if tagfilter is enabled.
+    pub fn write_raw_html(&mut self, value: &str) -> io::Result<()> {
+        if !self.tagfilter {
+            return self.write(value);
+        }
+        let mut begin = 0;
+        for (index, _) in value.match_indices('<') {
+            if is_disallowed_tag(&value[index + 1..]) {
+                self.write(&value[begin..index])?;
+                self.write("&lt;")?;
+                begin = index + 1;
+            }
+        }
+        self.write(&value[begin..])
+    }
+
+    /// Write given text with escaping some HTML special characters.
+    pub fn write_escaped_html(&mut self, value: &str) -> io::Result<()> {
+        let mut begin = 0;
+        for (index, byte) in value.bytes().enumerate() {
+            let escaped = match byte {
+                b'"' => "&quot;",
+                b'&' => "&amp;",
+                b'<' => "&lt;",
+                b'>' => "&gt;",
+                _ => continue,
+            };
+            self.write(&value[begin..index])?;
+            self.write(escaped)?;
+            begin = index + 1;
+        }
+        self.write(&value[begin..])
+    }
+
+    /// Write given URL with percent-encoding unsafe characters and escaping some HTML special characters.
+    pub fn write_escaped_href(&mut self, value: &str) -> io::Result<()> {
+        // Bytes to be written as they are are all ASCII, so any range of them is valid UTF-8.
+        let bytes = value.as_bytes();
+        let mut begin = 0;
+        for (index, &byte) in bytes.iter().enumerate() {
+            let escaped: Cow<str> = match byte {
+                b'&' => "&amp;".into(),
+                b'\'' => "&#x27;".into(),
+                b'-' | b'_' | b'.' | b'!' | b'~' | b'*' | b'(' | b')' | b';' | b'/' | b'?'
+                | b':' | b'@' | b'=' | b'+' | b'$' | b',' | b'%' | b'#' => continue,
+                _ if byte.is_ascii_alphanumeric() => continue,
+                _ => format!("%{:02X}", byte).into(),
+            };
+            self.write(std::str::from_utf8(&bytes[begin..index]).unwrap())?;
+            self.write(&escaped)?;
+            begin = index + 1;
+        }
+        self.write(std::str::from_utf8(&bytes[begin..]).unwrap())
+    }
+
+    /// Start collecting written text as a footnote definition, instead of writing it.
+    fn begin_footnote_buffer(&mut self) {
+        self.footnote_buffer = Some((String::new(), self.end_newline));
+        self.end_newline = true;
+    }
+
+    /// Stop collecting written text, and return the collected one if any.
+    fn end_footnote_buffer(&mut self) -> Option<String> {
+        let (buffer, end_newline) = self.footnote_buffer.take()?;
+        self.end_newline = end_newline;
+        Some(buffer)
+    }
+}
+
+/// Destination of HTML, so that the same renderer can write into String, `io::Write`, and `fmt::Write`.
+trait StrWrite {
+    fn write_str(&mut self, value: &str) -> io::Result<()>;
+}
+
+impl StrWrite for String {
+    fn write_str(&mut self, value: &str) -> io::Result<()> {
+        self.push_str(value);
+        Ok(())
+    }
+}
+
+struct IoWriter<W>(W);
+
+impl<W: io::Write> StrWrite for IoWriter<W> {
+    fn write_str(&mut self, value: &str) -> io::Result<()> {
+        self.0.write_all(value.as_bytes())
+    }
+}
+
+struct FmtWriter<W>(W);
+
+impl<W: fmt::Write> StrWrite for FmtWriter<W> {
+    fn write_str(&mut self, value: &str) -> io::Result<()> {
+        self.0
+            .write_str(value)
+            .map_err(|_| io::Error::new(io::ErrorKind::Other, "failed to write into fmt::Write"))
+    }
+}
+
+/// Dispatcher of events to `HtmlRenderer`, which keeps track of the state across events (e.g. footnote numbers).
+struct HtmlWriter<'a, 'w, I, R: ?Sized> {
+    iterator: I,
+    output: HtmlOutput<'w>,
+    renderer: &'w mut R,
+    /// Source text and its line index to calculate `data-sourcepos` from offsets of events.
+    source: Option<(&'a str, LineIndex)>,
+    // Table cells need to know their column's alignment, and whether they are in the head row.
+    table_alignments: Vec<Alignment>,
+    table_body_open: bool,
+    table_cell_index: usize,
+    table_head_open: bool,
+    // Footnotes are numbered in order of appearance, and their definitions may be collected to be written at the end.
+    footnote_section: bool,
+    footnote_numbers: HashMap<&'a str, usize>,
+    footnote_reference_counts: HashMap<&'a str, usize>,
+    footnotes: Vec<(&'a str, String)>,
+}
+
+impl<'a, 'w, I, R> HtmlWriter<'a, 'w, I, R>
+where
+    I: Iterator<Item = (Event<'a>, Range<usize>)>,
+    R: HtmlRenderer + ?Sized,
+{
+    fn new(
+        iterator: I,
+        text: Option<&'a str>,
+        writer: &'w mut dyn StrWrite,
+        options: Options,
+        renderer: &'w mut R,
+    ) -> Self {
+        Self {
+            iterator,
+            output: HtmlOutput::new(writer, options),
+            renderer,
+            source: text
+                .filter(|_| options.contains(Options::ENABLE_SOURCEPOS))
+                .map(|text| (text, LineIndex::new(text))),
+            table_alignments: Vec::new(),
+            table_body_open: false,
+            table_cell_index: 0,
+            table_head_open: false,
+            footnote_section: options.contains(Options::ENABLE_FOOTNOTE_SECTION),
+            footnote_numbers: HashMap::new(),
+            footnote_reference_counts: HashMap::new(),
+            footnotes: Vec::new(),
+        }
+    }
+
+    fn run(mut self) -> io::Result<()> {
+        while let Some((event, range)) = self.iterator.next() {
+            if let Some((text, line_index)) = &self.source {
+                // Trailing line endings and blank lines are not counted as part of blocks.
+                let end = range.start + text[range.clone()].trim_end().len().max(1);
+                self.output.sourcepos = Some((
+                    line_index.position(range.start),
+                    line_index.position(end - 1),
+                ));
+            }
+            let output = &mut self.output;
+            match event {
+                Begin(tag) => self.begin_tag(tag)?,
+                End(tag) => self.end_tag(tag)?,
+                Code(value) => self.renderer.code(output, &value)?,
+                FootnoteReference(label) => {
+                    let number = footnote_number(&mut self.footnote_numbers, label);
+                    let count = self.footnote_reference_counts.entry(label).or_insert(0);
+                    *count += 1;
+                    self.renderer
+                        .footnote_reference(output, label, number, *count)?;
                 }
-                Heading(level) => {
-                    writer.push_str(&format!("<h{}>", level as usize));
+                HardBreak => self.renderer.hard_break(output)?,
+                Html(value) => self.renderer.html(output, value)?,
+                InlineHtml(value) => self.renderer.inline_html(output, value)?,
+                SoftBreak => self.renderer.soft_break(output)?,
+                TaskListMarker(checked) => self.renderer.task_list_marker(output, checked)?,
+                Text(value) => self.renderer.text(output, &value)?,
+                ThematicBreak => self.renderer.thematic_break(output)?,
+            }
+        }
+        self.write_footnote_section()
+    }
+
+    fn begin_tag(&mut self, tag: Tag<'a>) -> io::Result<()> {
+        let output = &mut self.output;
+        match tag {
+            BlockQuote => self.renderer.begin_block_quote(output),
+            Emphasis => self.renderer.begin_emphasis(output),
+            FencedCodeBlock(info) => self.renderer.begin_fenced_code_block(output, &info),
+            FootnoteDefinition(label) => {
+                if self.footnote_section {
+                    output.begin_footnote_buffer();
+                    Ok(())
+                } else {
+                    let number = footnote_number(&mut self.footnote_numbers, label);
+                    self.renderer
+                        .begin_footnote_definition(output, label, number)
                 }
-                IndentedCodeBlock => {
-                    writer.push_str("<pre><code>");
+            }
+            Heading { level, id, classes } => {
+                self.renderer.begin_heading(output, level, id, &classes)
+            }
+            Image { dest, title } => {
+                let alt = self.raw_text();
+                self.renderer.image(&mut self.output, &dest, &title, &alt)
+            }
+            IndentedCodeBlock => self.renderer.begin_indented_code_block(output),
+            Item => self.renderer.begin_item(output),
+            Link { dest, title } => self.renderer.begin_link(output, &dest, &title),
+            List(start) => self.renderer.begin_list(output, start),
+            Paragraph => self.renderer.begin_paragraph(output),
+            Strikethrough => self.renderer.begin_strikethrough(output),
+            Strong => self.renderer.begin_strong(output),
+            Table(alignments) => {
+                self.renderer.begin_table(output, &alignments)?;
+                self.table_alignments = alignments;
+                self.table_body_open = false;
+                Ok(())
+            }
+            TableCell => {
+                let alignment = self
+                    .table_alignments
+                    .get(self.table_cell_index)
+                    .copied()
+                    .unwrap_or(Alignment::None);
+                self.renderer
+                    .begin_table_cell(output, alignment, self.table_head_open)
+            }
+            TableHead => {
+                self.table_cell_index = 0;
+                self.table_head_open = true;
+                self.renderer.begin_table_head(output)
+            }
+            TableRow => {
+                if !self.table_body_open {
+                    self.renderer.begin_table_body(output)?;
+                    self.table_body_open = true;
                 }
-                Paragraph => {
-                    writer.push_str("<p>");
+                self.table_cell_index = 0;
+                self.renderer.begin_table_row(output)
+            }
+        }
+    }
+
+    fn end_tag(&mut self, tag: Tag<'a>) -> io::Result<()> {
+        let output = &mut self.output;
+        match tag {
+            BlockQuote => self.renderer.end_block_quote(output),
+            Emphasis => self.renderer.end_emphasis(output),
+            FencedCodeBlock(info) => self.renderer.end_fenced_code_block(output, &info),
+            FootnoteDefinition(label) => match output.end_footnote_buffer() {
+                Some(content) => {
+                    self.footnotes.push((label, content));
+                    Ok(())
                 }
+                None => self.renderer.end_footnote_definition(output, label),
             },
-            End(tag) => match tag {
-                BlockQuote => {
-                    writer.push_str("</blockquote>\n");
+            Heading { level, .. } => self.renderer.end_heading(output, level),
+            Image { .. } => {
+                // Already rendered at its beginning event, which consumes events until the end.
+                Ok(())
+            }
+            IndentedCodeBlock => self.renderer.end_indented_code_block(output),
+            Item => self.renderer.end_item(output),
+            Link { dest, title } => self.renderer.end_link(output, &dest, &title),
+            List(start) => self.renderer.end_list(output, start),
+            Paragraph => self.renderer.end_paragraph(output),
+            Strikethrough => self.renderer.end_strikethrough(output),
+            Strong => self.renderer.end_strong(output),
+            Table(_) => {
+                if self.table_body_open {
+                    self.renderer.end_table_body(output)?;
                 }
-                FencedCodeBlock(_) | IndentedCodeBlock => {
-                    writer.push_str("</code></pre>\n");
+                self.renderer.end_table(output)
+            }
+            TableCell => {
+                self.table_cell_index += 1;
+                self.renderer.end_table_cell(output, self.table_head_open)
+            }
+            TableHead => {
+                self.table_head_open = false;
+                self.renderer.end_table_head(output)
+            }
+            TableRow => self.renderer.end_table_row(output),
+        }
+    }
+
+    /// Write collected footnote definitions in order of their numbers, with links back to their references.
+    /// Definitions without any reference are omitted.
+    fn write_footnote_section(&mut self) -> io::Result<()> {
+        let mut footnotes: Vec<_> = std::mem::take(&mut self.footnotes)
+            .into_iter()
+            .filter_map(|(label, content)| {
+                Some((*self.footnote_numbers.get(label)?, label, content))
+            })
+            .collect();
+        if footnotes.is_empty() {
+            return Ok(());
+        }
+        // The first definition wins if the same label is defined more than once.
+        footnotes.sort_by_key(|&(number, _, _)| number);
+        footnotes.dedup_by_key(|&mut (number, _, _)| number);
+
+        let output = &mut self.output;
+        output.write_line_ending_if_needed()?;
+        output.write("<section class=\"footnotes\">\n<ol>\n")?;
+        for (_, label, content) in footnotes {
+            let reference_count = self.footnote_reference_counts[label];
+            output.write("<li id=\"fn-")?;
+            output.write_escaped_html(label)?;
+            output.write("\">\n")?;
+            // Links back to references are put at the end of the last paragraph if possible.
+            match content.strip_suffix("</p>\n") {
+                Some(body) => {
+                    output.write(body)?;
+                    write_footnote_backrefs(output, label, reference_count, true)?;
+                    output.write("</p>\n")?;
                 }
-                Heading(level) => {
-                    writer.push_str(&format!("</h{}>\n", level as usize));
+                None => {
+                    output.write(&content)?;
+                    write_footnote_backrefs(output, label, reference_count, false)?;
+                    output.write("\n")?;
                 }
-                Paragraph => {
-                    writer.push_str("</p>\n");
+            }
+            output.write("</li>\n")?;
+        }
+        output.write("</ol>\n</section>\n")
+    }
+
+    /// Return text of events until the end of current element, without any HTML tags (e.g. for alt attribute).
+    fn raw_text(&mut self) -> String {
+        let mut text = String::new();
+        let mut depth = 0;
+        for (event, _) in self.iterator.by_ref() {
+            match event {
+                Begin(_) => {
+                    depth += 1;
                 }
-            },
-            Html(value) => {
-                writer.push_str(value);
-            }
-            Text(value) => {
-                for c in value.chars() {
-                    match c {
-                        '"' => writer.push_str("&quot;"),
-                        '&' => writer.push_str("&amp;"),
-                        '<' => writer.push_str("&lt;"),
-                        '>' => writer.push_str("&gt;"),
-                        _ => writer.write_char(c).unwrap(),
-                    }
+                End(_) if depth == 0 => break,
+                End(_) => {
+                    depth -= 1;
                 }
-            }
-            ThematicBreak => {
-                writer.push_str("<hr />\n");
+                Code(value) => {
+                    text.push_str(&value);
+                }
+                InlineHtml(value) => {
+                    text.push_str(value);
+                }
+                Text(value) => {
+                    text.push_str(&value);
+                }
+                HardBreak | SoftBreak => {
+                    text.push('\n');
+                }
+                FootnoteReference(_) | Html(_) | TaskListMarker(_) | ThematicBreak => {}
             }
         }
+        text
     }
 }
+
+/// Give empty offsets to given events, for the case where they are not needed.
+fn without_offsets<'a, I>(iterator: I) -> impl Iterator<Item = (Event<'a>, Range<usize>)>
+where
+    I: Iterator<Item = Event<'a>>,
+{
+    iterator.map(|event| (event, 0..0))
+}
+
+/// Return number of given footnote, which is assigned in order of first appearance.
+fn footnote_number<'a>(numbers: &mut HashMap<&'a str, usize>, label: &'a str) -> usize {
+    let next_number = numbers.len() + 1;
+    *numbers.entry(label).or_insert(next_number)
+}
+
+/// Write links back to all references to given footnote, separated by spaces.
+fn write_footnote_backrefs(
+    output: &mut HtmlOutput,
+    label: &str,
+    reference_count: usize,
+    leading_space: bool,
+) -> io::Result<()> {
+    for count in 1..=reference_count {
+        if leading_space || count > 1 {
+            output.write(" ")?;
+        }
+        output.write(r##"<a href="#"##)?;
+        write_footnote_reference_id(output, label, count)?;
+        output.write(r#"" class="footnote-backref">↩"#)?;
+        if count > 1 {
+            output.write(&format!("<sup>{}</sup>", count))?;
+        }
+        output.write("</a>")?;
+    }
+    Ok(())
+}
+
+/// Write id of footnote reference, which is suffixed by its count from the second reference to the same footnote.
+fn write_footnote_reference_id(
+    output: &mut HtmlOutput,
+    label: &str,
+    count: usize,
+) -> io::Result<()> {
+    output.write("fnref-")?;
+    output.write_escaped_html(label)?;
+    if count > 1 {
+        output.write(&format!("-{}", count))?;
+    }
+    Ok(())
+}
+
+/// Check if given text (just after `<`) begins with open or closing tag disallowed by tagfilter (e.g. `script>`).
+fn is_disallowed_tag(value: &str) -> bool {
+    let value = value.strip_prefix('/').unwrap_or(value).as_bytes();
+    let names: [&[u8]; 9] = [
+        b"title",
+        b"textarea",
+        b"style",
+        b"xmp",
+        b"iframe",
+        b"noembed",
+        b"noframes",
+        b"script",
+        b"plaintext",
+    ];
+    names.iter().any(|name| {
+        value.len() > name.len()
+            && value[..name.len()].eq_ignore_ascii_case(name)
+            && match value[name.len()] {
+                b'>' => true,
+                b'/' => value.get(name.len() + 1) == Some(&b'>'),
+                byte => byte.is_ascii_whitespace(),
+            }
+    })
+}
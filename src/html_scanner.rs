@@ -0,0 +1,173 @@
+/// Check if inline raw HTML (i.e. open tag, closing tag, comment, processing instruction, declaration, or CDATA section)
+/// starts from given index, and return its length if found.
+pub fn scan_raw_html(text: &str, begin: usize) -> Option<usize> {
+    scan_open_tag(text, begin)
+        .or_else(|| scan_closing_tag(text, begin))
+        .or_else(|| scan_comment(text, begin))
+        .or_else(|| scan_processing_instruction(text, begin))
+        .or_else(|| scan_declaration(text, begin))
+        .or_else(|| scan_cdata_section(text, begin))
+}
+
+/// Check if open tag (e.g. `<a href="/">`) starts from given index, and return its length if found.
+pub fn scan_open_tag(text: &str, begin: usize) -> Option<usize> {
+    let bytes = text.as_bytes();
+    if bytes.get(begin) != Some(&b'<') {
+        return None;
+    }
+    let mut index = begin + 1;
+    index += scan_tag_name(text, index)?;
+    loop {
+        let whitespaces_length = scan_whitespaces(text, index);
+        if whitespaces_length == 0 {
+            break;
+        }
+        match scan_attribute(text, index + whitespaces_length) {
+            Some(attribute_length) => {
+                index += whitespaces_length + attribute_length;
+            }
+            None => break,
+        }
+    }
+    index += scan_whitespaces(text, index);
+    if bytes.get(index) == Some(&b'/') {
+        index += 1;
+    }
+    if bytes.get(index) == Some(&b'>') {
+        Some(index + 1 - begin)
+    } else {
+        None
+    }
+}
+
+/// Check if closing tag (e.g. `</a>`) starts from given index, and return its length if found.
+pub fn scan_closing_tag(text: &str, begin: usize) -> Option<usize> {
+    if !text[begin..].starts_with("</") {
+        return None;
+    }
+    let mut index = begin + 2;
+    index += scan_tag_name(text, index)?;
+    index += scan_whitespaces(text, index);
+    if text[index..].starts_with('>') {
+        Some(index + 1 - begin)
+    } else {
+        None
+    }
+}
+
+/// Check if HTML comment (e.g. `<!-- comment -->`) starts from given index, and return its length if found.
+fn scan_comment(text: &str, begin: usize) -> Option<usize> {
+    let body = text[begin..].strip_prefix("<!--")?;
+    if body.starts_with('>') || body.starts_with("->") {
+        return None;
+    }
+    // Comment cannot contain `--`, and cannot end with `-`.
+    let position = body.find("--")?;
+    if body[position..].starts_with("-->") {
+        Some(position + 7)
+    } else {
+        None
+    }
+}
+
+/// Check if processing instruction (e.g. `<?php echo 1; ?>`) starts from given index, and return its length if found.
+fn scan_processing_instruction(text: &str, begin: usize) -> Option<usize> {
+    let body = text[begin..].strip_prefix("<?")?;
+    Some(body.find("?>")? + 4)
+}
+
+/// Check if declaration (e.g. `<!DOCTYPE html>`) starts from given index, and return its length if found.
+fn scan_declaration(text: &str, begin: usize) -> Option<usize> {
+    let body = text[begin..].strip_prefix("<!")?;
+    let name_length = body
+        .bytes()
+        .take_while(|byte| byte.is_ascii_uppercase())
+        .count();
+    if name_length == 0 || scan_whitespaces(body, name_length) == 0 {
+        return None;
+    }
+    Some(body.find('>')? + 3)
+}
+
+/// Check if CDATA section (e.g. `<![CDATA[text]]>`) starts from given index, and return its length if found.
+fn scan_cdata_section(text: &str, begin: usize) -> Option<usize> {
+    let body = text[begin..].strip_prefix("<![CDATA[")?;
+    Some(body.find("]]>")? + 12)
+}
+
+/// Check if tag name starts from given index, and return its length if found.
+fn scan_tag_name(text: &str, begin: usize) -> Option<usize> {
+    let bytes = &text.as_bytes()[begin..];
+    if !bytes.first()?.is_ascii_alphabetic() {
+        return None;
+    }
+    Some(
+        1 + bytes[1..]
+            .iter()
+            .take_while(|&&byte| byte.is_ascii_alphanumeric() || byte == b'-')
+            .count(),
+    )
+}
+
+/// Check if HTML tag's attribute part starts from given index, and return its length if found.
+fn scan_attribute(text: &str, begin: usize) -> Option<usize> {
+    let mut index = begin;
+    index += scan_attribute_name(text, index)?;
+    let whitespaces_length = scan_whitespaces(text, index);
+    if text[index + whitespaces_length..].starts_with('=') {
+        index += whitespaces_length + 1;
+        index += scan_whitespaces(text, index);
+        index += scan_attribute_value(text, index)?;
+    }
+    Some(index - begin)
+}
+
+/// Check if HTML tag's attribute name starts from given index, and return its length if found.
+fn scan_attribute_name(text: &str, begin: usize) -> Option<usize> {
+    let bytes = &text.as_bytes()[begin..];
+    match bytes.first()? {
+        byte if byte.is_ascii_alphabetic() || *byte == b'_' || *byte == b':' => Some(
+            1 + bytes[1..]
+                .iter()
+                .take_while(|&&byte| {
+                    byte.is_ascii_alphanumeric() || matches!(byte, b'_' | b':' | b'.' | b'-')
+                })
+                .count(),
+        ),
+        _ => None,
+    }
+}
+
+/// Check if HTML tag's attribute value starts from given index, and return its length if found.
+fn scan_attribute_value(text: &str, begin: usize) -> Option<usize> {
+    let bytes = &text.as_bytes()[begin..];
+    match *bytes.first()? {
+        quote @ (b'"' | b'\'') => Some(bytes[1..].iter().position(|&byte| byte == quote)? + 2),
+        _ => {
+            let length = bytes
+                .iter()
+                .take_while(|&&byte| {
+                    !is_whitespace(byte)
+                        && !matches!(byte, b'"' | b'\'' | b'=' | b'<' | b'>' | b'`')
+                })
+                .count();
+            if length == 0 {
+                None
+            } else {
+                Some(length)
+            }
+        }
+    }
+}
+
+/// Return length of 0 or more whitespaces (including line endings) from given index.
+fn scan_whitespaces(text: &str, begin: usize) -> usize {
+    text.as_bytes()[begin..]
+        .iter()
+        .take_while(|&&byte| is_whitespace(byte))
+        .count()
+}
+
+fn is_whitespace(byte: u8) -> bool {
+    (0x09..=0x0d).contains(&byte) || byte == b' '
+}
@@ -0,0 +1,1250 @@
+use crate::entities;
+use crate::extended_autolink;
+use crate::html_scanner;
+use crate::types::{Event, LinkReferenceDefinition, Options, Tag};
+use crate::unicode::{is_punctuation, is_whitespace};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// Convert text segments of a leaf block (e.g. lines of a paragraph) into inline-level events.
+pub(crate) struct Parser<'a> {
+    brackets: Vec<Bracket>,
+    content: String,
+    delimiters: Vec<usize>,
+    items: Vec<Item<'a>>,
+    options: Options,
+    segments: Vec<Segment>,
+    text: &'a str,
+}
+
+impl<'a> Parser<'a> {
+    pub(crate) fn new(text: &'a str, ranges: Vec<Range<usize>>, options: Options) -> Self {
+        let mut content = String::new();
+        let mut segments = Vec::new();
+        for range in ranges {
+            segments.push(Segment {
+                begin: content.len(),
+                source: range.clone(),
+            });
+            content.push_str(&text[range]);
+        }
+        Self {
+            brackets: Vec::new(),
+            content,
+            delimiters: Vec::new(),
+            items: Vec::new(),
+            options,
+            segments,
+            text,
+        }
+    }
+
+    /// Parse link reference definitions from the beginning of content,
+    /// and return them with the number of segments they occupy.
+    pub(crate) fn parse_link_reference_definitions(
+        &self,
+    ) -> (Vec<(String, LinkReferenceDefinition<'a>)>, usize) {
+        let mut definitions = Vec::new();
+        let mut index = 0;
+        while let Some((length, ranges)) = self.scan_link_reference_definition(index) {
+            definitions.push((
+                normalize_link_label(&self.content[ranges.label]),
+                LinkReferenceDefinition {
+                    dest: self.unescaped(ranges.destination),
+                    title: ranges
+                        .title
+                        .map_or(Cow::Borrowed(""), |title| self.unescaped(title)),
+                },
+            ));
+            index += length;
+        }
+        let count = self
+            .segments
+            .iter()
+            .take_while(|segment| segment.end() <= index)
+            .count();
+        (definitions, count)
+    }
+
+    pub(crate) fn run(
+        mut self,
+        definitions: &HashMap<String, LinkReferenceDefinition<'a>>,
+        footnote_definitions: &HashMap<String, &'a str>,
+    ) -> Vec<(Event<'a>, Range<usize>)> {
+        // Final spaces and tabs are not part of the content.
+        let length = self.content.trim_end_matches(&[' ', '\t'][..]).len();
+        self.content.truncate(length);
+
+        let smart_punctuation = self.options.contains(Options::ENABLE_SMART_PUNCTUATION);
+        let mut index = 0;
+        let mut text_begin = 0;
+        while index < self.content.len() {
+            match self.content.as_bytes()[index] {
+                b'\\' => match self.content.as_bytes().get(index + 1) {
+                    Some(byte) if byte.is_ascii_punctuation() => {
+                        // Escaped character is left as text, without its backslash.
+                        self.push_text(text_begin, index);
+                        text_begin = index + 1;
+                        index += 2;
+                    }
+                    Some(b'\n' | b'\r') => {
+                        self.push_text(text_begin, index);
+                        index = self.parse_line_break(index, index + 1, Event::HardBreak);
+                        text_begin = index;
+                    }
+                    _ => {
+                        index += 1;
+                    }
+                },
+                b'&' => {
+                    if let Some((length, value)) = scan_character_reference(&self.content[index..])
+                    {
+                        self.push_text(text_begin, index);
+                        self.items.push(Item {
+                            begin: index,
+                            end: index + length,
+                            kind: ItemKind::Event(Event::Text(value)),
+                        });
+                        index += length;
+                        text_begin = index;
+                    } else {
+                        index += 1;
+                    }
+                }
+                b'<' => {
+                    if let Some((length, address, email)) = self.scan_autolink(index) {
+                        self.push_text(text_begin, index);
+                        index = self.parse_autolink(index, length, address, email);
+                        text_begin = index;
+                    } else if let Some(length) = html_scanner::scan_raw_html(&self.content, index) {
+                        self.push_text(text_begin, index);
+                        self.items.push(Item {
+                            begin: index,
+                            end: index + length,
+                            kind: ItemKind::Html,
+                        });
+                        index += length;
+                        text_begin = index;
+                    } else {
+                        index += 1;
+                    }
+                }
+                b'\n' | b'\r' => {
+                    let spaces_begin = self.content[..index].trim_end_matches(' ').len();
+                    let event = if index - spaces_begin >= 2 {
+                        Event::HardBreak
+                    } else {
+                        Event::SoftBreak
+                    };
+                    self.push_text(text_begin, spaces_begin);
+                    index = self.parse_line_break(spaces_begin, index, event);
+                    text_begin = index;
+                }
+                b'`' => {
+                    if let Some(length) = self.scan_code_span(index) {
+                        self.push_text(text_begin, index);
+                        index = self.parse_code_span(index, length);
+                        text_begin = index;
+                    } else {
+                        index += self.scan_repeated_byte(index, b'`');
+                    }
+                }
+                b'*' | b'_' => {
+                    self.push_text(text_begin, index);
+                    index = self.parse_delimiter_run(index);
+                    text_begin = index;
+                }
+                b'~' if self.options.contains(Options::ENABLE_STRIKETHROUGH) => {
+                    // Strikethrough needs one or two tildes, and longer runs are left as text.
+                    let length = self.scan_repeated_byte(index, b'~');
+                    if length <= 2 {
+                        self.push_text(text_begin, index);
+                        index = self.parse_delimiter_run(index);
+                        text_begin = index;
+                    } else {
+                        index += length;
+                    }
+                }
+                b'"' | b'\'' if smart_punctuation => {
+                    self.push_text(text_begin, index);
+                    index = self.parse_smart_quote(index);
+                    text_begin = index;
+                }
+                b'-' if smart_punctuation && self.content[index + 1..].starts_with('-') => {
+                    self.push_text(text_begin, index);
+                    index = self.parse_smart_dashes(index);
+                    text_begin = index;
+                }
+                b'.' if smart_punctuation && self.content[index..].starts_with("...") => {
+                    self.push_text(text_begin, index);
+                    self.items.push(Item {
+                        begin: index,
+                        end: index + 3,
+                        kind: ItemKind::Event(Event::Text(Cow::Borrowed("…"))),
+                    });
+                    index += 3;
+                    text_begin = index;
+                }
+                b'!' if self.content[index + 1..].starts_with('[') => {
+                    self.push_text(text_begin, index);
+                    index = self.parse_opening_bracket(index, true);
+                    text_begin = index;
+                }
+                b'[' => {
+                    self.push_text(text_begin, index);
+                    index = self.parse_opening_bracket(index, false);
+                    text_begin = index;
+                }
+                b']' => {
+                    self.push_text(text_begin, index);
+                    index = self.parse_closing_bracket(index, definitions, footnote_definitions);
+                    text_begin = index;
+                }
+                _ => {
+                    index += 1;
+                }
+            }
+        }
+        self.push_text(text_begin, index);
+        self.process_emphasis(0);
+        if self.options.contains(Options::ENABLE_EXTENDED_AUTOLINKS) {
+            extended_autolink::parse_extended_autolinks(self.into_events())
+        } else {
+            self.into_events()
+        }
+    }
+
+    /// Parse code span of given length, and return index after parse.
+    fn parse_code_span(&mut self, begin: usize, length: usize) -> usize {
+        let end = begin + length;
+        let backticks_length = self.scan_repeated_byte(begin, b'`');
+        let mut content_begin = begin + backticks_length;
+        let mut content_end = end - backticks_length;
+        let bytes = self.content.as_bytes();
+        if bytes[content_begin..content_end]
+            .iter()
+            .any(|&byte| byte != b' ' && !is_line_ending(byte))
+        {
+            if let (Some(head), Some(tail)) = (
+                self.scan_space_or_line_ending(content_begin),
+                self.scan_space_or_line_ending_backward(content_end),
+            ) {
+                content_begin += head;
+                content_end -= tail;
+            }
+        }
+        // Line endings are converted into spaces, so source can be borrowed only without them.
+        let value = match self
+            .source(content_begin..content_end)
+            .filter(|value| !value.contains(&['\r', '\n'][..]))
+        {
+            Some(value) => Cow::Borrowed(value),
+            None => Cow::Owned(
+                self.content[content_begin..content_end]
+                    .replace("\r\n", " ")
+                    .replace(&['\r', '\n'][..], " "),
+            ),
+        };
+        self.items.push(Item {
+            begin,
+            end,
+            kind: ItemKind::Event(Event::Code(value)),
+        });
+        end
+    }
+
+    /// Parse `"` or `'` as curly quote, which is opening one if it can only open emphasis, and return index after parse.
+    fn parse_smart_quote(&mut self, begin: usize) -> usize {
+        let previous = self.content[..begin].chars().next_back();
+        let next = self.content[begin + 1..].chars().next();
+        let opening = is_left_flanking(previous, next) && !is_left_flanking(next, previous);
+        let value = match (self.content.as_bytes()[begin], opening) {
+            (b'"', true) => "“",
+            (b'"', false) => "”",
+            (_, true) => "‘",
+            (_, false) => "’",
+        };
+        self.items.push(Item {
+            begin,
+            end: begin + 1,
+            kind: ItemKind::Event(Event::Text(Cow::Borrowed(value))),
+        });
+        begin + 1
+    }
+
+    /// Parse a run of 2 or more hyphens as en dashes and em dashes, and return index after parse.
+    /// As CommonMark reference implementation does, em dashes are preferred, and then en dashes.
+    fn parse_smart_dashes(&mut self, begin: usize) -> usize {
+        let length = self.scan_repeated_byte(begin, b'-');
+        let (em_dashes_count, en_dashes_count) = if length % 3 == 0 {
+            (length / 3, 0)
+        } else if length % 2 == 0 {
+            (0, length / 2)
+        } else if length % 3 == 2 {
+            ((length - 2) / 3, 1)
+        } else {
+            ((length - 4) / 3, 2)
+        };
+        let end = begin + length;
+        self.items.push(Item {
+            begin,
+            end,
+            kind: ItemKind::Event(Event::Text(Cow::Owned(
+                "—".repeat(em_dashes_count) + &"–".repeat(en_dashes_count),
+            ))),
+        });
+        end
+    }
+
+    /// Parse autolink of given length and address, and return index after parse.
+    fn parse_autolink(
+        &mut self,
+        begin: usize,
+        length: usize,
+        address: &'a str,
+        email: bool,
+    ) -> usize {
+        let end = begin + length;
+        let tag = Tag::Link {
+            dest: if email {
+                Cow::Owned(format!("mailto:{}", address))
+            } else {
+                Cow::Borrowed(address)
+            },
+            title: Cow::Borrowed(""),
+        };
+        self.items.push(Item {
+            begin,
+            end: begin + 1,
+            kind: ItemKind::Event(Event::Begin(tag.clone())),
+        });
+        self.items.push(Item {
+            begin: begin + 1,
+            end: end - 1,
+            kind: ItemKind::Event(Event::Text(Cow::Borrowed(address))),
+        });
+        self.items.push(Item {
+            begin: end - 1,
+            end,
+            kind: ItemKind::Event(Event::End(tag)),
+        });
+        end
+    }
+
+    /// Parse line ending at given index as line break which begins from given index, and return index after parse.
+    fn parse_line_break(&mut self, begin: usize, line_ending: usize, event: Event<'a>) -> usize {
+        let end = line_ending
+            + if self.content[line_ending..].starts_with("\r\n") {
+                2
+            } else {
+                1
+            };
+        self.items.push(Item {
+            begin,
+            end,
+            kind: ItemKind::Event(event),
+        });
+        end
+    }
+
+    /// Parse `[` or `![`, push it onto the bracket stack, and return index after parse.
+    fn parse_opening_bracket(&mut self, begin: usize, image: bool) -> usize {
+        let end = begin + if image { 2 } else { 1 };
+        self.brackets.push(Bracket {
+            active: true,
+            delimiters_length: self.delimiters.len(),
+            image,
+            item: self.items.len(),
+        });
+        self.push_text(begin, end);
+        end
+    }
+
+    /// Parse `]` as the end of link, image, or footnote reference if possible, otherwise as text,
+    /// and return index after parse.
+    fn parse_closing_bracket(
+        &mut self,
+        begin: usize,
+        definitions: &HashMap<String, LinkReferenceDefinition<'a>>,
+        footnote_definitions: &HashMap<String, &'a str>,
+    ) -> usize {
+        let end = begin + 1;
+        let bracket = match self.brackets.pop() {
+            Some(bracket) if bracket.active => bracket,
+            _ => {
+                self.push_text(begin, end);
+                return end;
+            }
+        };
+        let link = match self.scan_inline_link(end) {
+            Some((length, destination, title)) => Some((
+                length,
+                self.unescaped(destination),
+                title.map_or(Cow::Borrowed(""), |title| self.unescaped(title)),
+            )),
+            None => {
+                if let Some(label) =
+                    self.scan_footnote_reference(&bracket, begin, footnote_definitions)
+                {
+                    return self.parse_footnote_reference(&bracket, end, label);
+                }
+                self.scan_reference_link(&bracket, begin, definitions)
+            }
+        };
+        let (length, dest, title) = match link {
+            Some(link) => link,
+            None => {
+                self.push_text(begin, end);
+                return end;
+            }
+        };
+        let tag = if bracket.image {
+            Tag::Image { dest, title }
+        } else {
+            Tag::Link { dest, title }
+        };
+        self.process_emphasis(bracket.delimiters_length);
+        self.items[bracket.item].kind = ItemKind::Event(Event::Begin(tag.clone()));
+        self.items.push(Item {
+            begin,
+            end: end + length,
+            kind: ItemKind::Event(Event::End(tag)),
+        });
+        if !bracket.image {
+            // Links may not contain other links.
+            for bracket in &mut self.brackets {
+                if !bracket.image {
+                    bracket.active = false;
+                }
+            }
+        }
+        end + length
+    }
+
+    /// Replace the bracket and its content with footnote reference of given label, and return given index.
+    fn parse_footnote_reference(&mut self, bracket: &Bracket, end: usize, label: &'a str) -> usize {
+        let begin = self.items[bracket.item].begin;
+        self.items.truncate(bracket.item);
+        self.delimiters.truncate(bracket.delimiters_length);
+        self.items.push(Item {
+            begin,
+            end,
+            kind: ItemKind::Event(Event::FootnoteReference(label)),
+        });
+        end
+    }
+
+    /// Parse a run of `*`, `_`, or `~`, push it onto the delimiter stack, and return index after parse.
+    fn parse_delimiter_run(&mut self, begin: usize) -> usize {
+        let bytes = self.content.as_bytes();
+        let byte = bytes[begin];
+        let end = begin + bytes[begin..].iter().take_while(|&&b| b == byte).count();
+        let previous = self.content[..begin].chars().next_back();
+        let next = self.content[end..].chars().next();
+        let left_flanking = is_left_flanking(previous, next);
+        let right_flanking = is_left_flanking(next, previous);
+        let (can_open, can_close) = if byte != b'_' {
+            (left_flanking, right_flanking)
+        } else {
+            (
+                left_flanking && (!right_flanking || previous.map_or(false, is_punctuation)),
+                right_flanking && (!left_flanking || next.map_or(false, is_punctuation)),
+            )
+        };
+        self.delimiters.push(self.items.len());
+        self.items.push(Item {
+            begin,
+            end,
+            kind: ItemKind::Delimiter(Delimiter {
+                active: true,
+                byte,
+                can_close,
+                can_open,
+                closings: Vec::new(),
+                length: end - begin,
+                openings: Vec::new(),
+            }),
+        });
+        end
+    }
+
+    /// Check if URI autolink (e.g. `<https://example.com>`) or email autolink (e.g. `<foo@example.com>`) starts from given index,
+    /// and return its length, address, and whether it is email autolink if found.
+    fn scan_autolink(&self, begin: usize) -> Option<(usize, &'a str, bool)> {
+        let bytes = self.content.as_bytes();
+        let address_begin = begin + 1;
+        let (address_length, email) = if let Some(length) = self.scan_uri(address_begin) {
+            (length, false)
+        } else {
+            (self.scan_email_address(address_begin)?, true)
+        };
+        let address_end = address_begin + address_length;
+        if bytes.get(address_end) != Some(&b'>') {
+            return None;
+        }
+        let address = self.source(address_begin..address_end)?;
+        Some((address_length + 2, address, email))
+    }
+
+    /// Check if absolute URI (e.g. `https://example.com`) starts from given index, and return its length if found.
+    fn scan_uri(&self, begin: usize) -> Option<usize> {
+        let bytes = &self.content.as_bytes()[begin..];
+        if !bytes.first()?.is_ascii_alphabetic() {
+            return None;
+        }
+        let scheme_length = 1 + bytes[1..]
+            .iter()
+            .take_while(|&&byte| byte.is_ascii_alphanumeric() || matches!(byte, b'+' | b'.' | b'-'))
+            .count();
+        if !(2..=32).contains(&scheme_length) || bytes.get(scheme_length) != Some(&b':') {
+            return None;
+        }
+        Some(
+            scheme_length
+                + 1
+                + bytes[scheme_length + 1..]
+                    .iter()
+                    .take_while(|&&byte| {
+                        !matches!(byte, b' ' | b'<' | b'>') && !byte.is_ascii_control()
+                    })
+                    .count(),
+        )
+    }
+
+    /// Check if email address (e.g. `foo@example.com`) starts from given index, and return its length if found.
+    fn scan_email_address(&self, begin: usize) -> Option<usize> {
+        let bytes = &self.content.as_bytes()[begin..];
+        let local_part_length = bytes
+            .iter()
+            .take_while(|&&byte| {
+                byte.is_ascii_alphanumeric() || b".!#$%&'*+/=?^_`{|}~-".contains(&byte)
+            })
+            .count();
+        if local_part_length == 0 || bytes.get(local_part_length) != Some(&b'@') {
+            return None;
+        }
+        let mut index = local_part_length + 1;
+        loop {
+            let label_length = bytes[index..]
+                .iter()
+                .take_while(|&&byte| byte.is_ascii_alphanumeric() || byte == b'-')
+                .count();
+            let label = &bytes[index..index + label_length];
+            if !(1..=63).contains(&label_length)
+                || label[0] == b'-'
+                || label[label_length - 1] == b'-'
+            {
+                return None;
+            }
+            index += label_length;
+            if bytes.get(index) == Some(&b'.') {
+                index += 1;
+            } else {
+                return Some(index);
+            }
+        }
+    }
+
+    /// Check if code span starts from given index, and return its length if found.
+    fn scan_code_span(&self, begin: usize) -> Option<usize> {
+        let length = self.scan_repeated_byte(begin, b'`');
+        let mut index = begin + length;
+        while let Some(position) = self.content[index..].find('`') {
+            index += position;
+            let closing_length = self.scan_repeated_byte(index, b'`');
+            index += closing_length;
+            if closing_length == length {
+                return Some(index - begin);
+            }
+        }
+        None
+    }
+
+    /// Check if inline link's destination and title part (e.g. `(/url "title")`) starts from given index,
+    /// and return its length, destination range, and title range if found.
+    fn scan_inline_link(
+        &self,
+        begin: usize,
+    ) -> Option<(usize, Range<usize>, Option<Range<usize>>)> {
+        if !self.content[begin..].starts_with('(') {
+            return None;
+        }
+        let mut index = self.parse_whitespaces(begin + 1);
+        let mut destination = index..index;
+        let mut title = None;
+        if let Some((length, range)) = self.scan_link_destination(index) {
+            destination = range;
+            index += length;
+            let index_after_whitespaces = self.parse_whitespaces(index);
+            if index_after_whitespaces > index {
+                if let Some(length) = self.scan_link_title(index_after_whitespaces) {
+                    title = Some(index_after_whitespaces + 1..index_after_whitespaces + length - 1);
+                    index = index_after_whitespaces + length;
+                }
+            }
+        }
+        index = self.parse_whitespaces(index);
+        if !self.content[index..].starts_with(')') {
+            return None;
+        }
+        Some((index + 1 - begin, destination, title))
+    }
+
+    /// Check if reference link's label part (e.g. `[label]`, `[]`, or nothing) starts from given index (just after `]`),
+    /// and return its length, destination, and title if its definition is found.
+    fn scan_reference_link(
+        &self,
+        bracket: &Bracket,
+        begin: usize,
+        definitions: &HashMap<String, LinkReferenceDefinition<'a>>,
+    ) -> Option<(usize, Cow<'a, str>, Cow<'a, str>)> {
+        let end = begin + 1;
+        let (length, label) = if self.content[end..].starts_with("[]") {
+            (2, None)
+        } else if let Some(length) = self.scan_link_label(end) {
+            (length, Some(end + 1..end + length - 1))
+        } else {
+            (0, None)
+        };
+        let label = match label {
+            Some(label) => label,
+            None => {
+                // Collapsed and shortcut reference links use their link text as label.
+                let label_begin = self.items[bracket.item].end - 1;
+                if self.scan_link_label(label_begin) != Some(end - label_begin) {
+                    return None;
+                }
+                label_begin + 1..begin
+            }
+        };
+        let definition = definitions.get(&normalize_link_label(&self.content[label]))?;
+        Some((length, definition.dest.clone(), definition.title.clone()))
+    }
+
+    /// Check if footnote reference's label (e.g. `^1` of `[^1]`) is between given bracket and `]` at given index,
+    /// and return the label of its definition if defined.
+    fn scan_footnote_reference(
+        &self,
+        bracket: &Bracket,
+        begin: usize,
+        footnote_definitions: &HashMap<String, &'a str>,
+    ) -> Option<&'a str> {
+        if bracket.image || footnote_definitions.is_empty() {
+            return None;
+        }
+        let label = self.content[self.items[bracket.item].end..begin].strip_prefix('^')?;
+        if label.is_empty() || label.contains(is_whitespace) {
+            return None;
+        }
+        footnote_definitions
+            .get(&normalize_link_label(label))
+            .copied()
+    }
+
+    /// Check if link reference definition (e.g. `[label]: /url "title"`) starts from given index,
+    /// and return its length (including line ending) and ranges of its parts if found.
+    fn scan_link_reference_definition(
+        &self,
+        begin: usize,
+    ) -> Option<(usize, LinkReferenceDefinitionRanges)> {
+        let mut index = begin;
+        let label_length = self.scan_link_label(index)?;
+        let label = index + 1..index + label_length - 1;
+        index += label_length;
+        if !self.content[index..].starts_with(':') {
+            return None;
+        }
+        index = self.parse_whitespaces(index + 1);
+        let (destination_length, destination) = self.scan_link_destination(index)?;
+        index += destination_length;
+
+        let index_after_whitespaces = self.parse_whitespaces(index);
+        if index_after_whitespaces > index {
+            if let Some(title_length) = self.scan_link_title(index_after_whitespaces) {
+                let title_end = index_after_whitespaces + title_length;
+                if let Some(length) = self.scan_line_end(title_end) {
+                    return Some((
+                        title_end + length - begin,
+                        LinkReferenceDefinitionRanges {
+                            label,
+                            destination,
+                            title: Some(index_after_whitespaces + 1..title_end - 1),
+                        },
+                    ));
+                }
+            }
+        }
+        let length = self.scan_line_end(index)?;
+        Some((
+            index + length - begin,
+            LinkReferenceDefinitionRanges {
+                label,
+                destination,
+                title: None,
+            },
+        ))
+    }
+
+    /// Check if link label (e.g. `[label]`) starts from given index, and return its length if found.
+    fn scan_link_label(&self, begin: usize) -> Option<usize> {
+        let bytes = self.content.as_bytes();
+        if bytes.get(begin) != Some(&b'[') {
+            return None;
+        }
+        let mut index = begin + 1;
+        loop {
+            match *bytes.get(index)? {
+                b']' => break,
+                b'[' => return None,
+                b'\\' if bytes.get(index + 1).map_or(false, u8::is_ascii_punctuation) => {
+                    index += 2;
+                }
+                _ => {
+                    index += 1;
+                }
+            }
+        }
+        let label = &self.content[begin + 1..index];
+        if label.chars().count() > 999 || label.chars().all(is_whitespace) {
+            return None;
+        }
+        Some(index + 1 - begin)
+    }
+
+    /// Check if link destination starts from given index, and return its length and value range if found.
+    fn scan_link_destination(&self, begin: usize) -> Option<(usize, Range<usize>)> {
+        let bytes = self.content.as_bytes();
+        let mut index = begin;
+        if bytes.get(index) == Some(&b'<') {
+            index += 1;
+            while index < bytes.len() {
+                match bytes[index] {
+                    b'>' => return Some((index + 1 - begin, begin + 1..index)),
+                    b'<' | b'\n' | b'\r' => return None,
+                    b'\\' if bytes.get(index + 1).map_or(false, u8::is_ascii_punctuation) => {
+                        index += 2;
+                    }
+                    _ => {
+                        index += 1;
+                    }
+                }
+            }
+            return None;
+        }
+
+        let mut depth = 0;
+        while index < bytes.len() {
+            match bytes[index] {
+                b'(' => {
+                    depth += 1;
+                }
+                b')' if depth == 0 => break,
+                b')' => {
+                    depth -= 1;
+                }
+                b'\\' if bytes.get(index + 1).map_or(false, u8::is_ascii_punctuation) => {
+                    index += 1;
+                }
+                byte if byte == b' ' || byte.is_ascii_control() => break,
+                _ => {}
+            }
+            index += 1;
+        }
+        if index == begin || depth != 0 {
+            None
+        } else {
+            Some((index - begin, begin..index))
+        }
+    }
+
+    /// Check if link title (e.g. `"title"`) starts from given index, and return its length if found.
+    fn scan_link_title(&self, begin: usize) -> Option<usize> {
+        let bytes = self.content.as_bytes();
+        let closing = match *bytes.get(begin)? {
+            b'"' => b'"',
+            b'\'' => b'\'',
+            b'(' => b')',
+            _ => return None,
+        };
+        let mut index = begin + 1;
+        while index < bytes.len() {
+            match bytes[index] {
+                byte if byte == closing => return Some(index + 1 - begin),
+                b'(' if closing == b')' => return None,
+                b'\\' if bytes.get(index + 1).map_or(false, u8::is_ascii_punctuation) => {
+                    index += 2;
+                }
+                _ => {
+                    index += 1;
+                }
+            }
+        }
+        None
+    }
+
+    /// Check if 0 or more spaces or tabs and a line ending (or the end of content) start from given index,
+    /// and return its length if found.
+    fn scan_line_end(&self, begin: usize) -> Option<usize> {
+        let bytes = self.content.as_bytes();
+        let index = begin
+            + bytes[begin..]
+                .iter()
+                .take_while(|&&byte| byte == b' ' || byte == b'\t')
+                .count();
+        match bytes.get(index) {
+            None => Some(index - begin),
+            Some(b'\n') => Some(index + 1 - begin),
+            Some(b'\r') => Some(
+                index - begin
+                    + if bytes.get(index + 1) == Some(&b'\n') {
+                        2
+                    } else {
+                        1
+                    },
+            ),
+            _ => None,
+        }
+    }
+
+    /// Parse 0 or more spaces, tabs, or line endings, and return index after parse.
+    fn parse_whitespaces(&self, index: usize) -> usize {
+        index
+            + self.content.as_bytes()[index..]
+                .iter()
+                .take_while(|&&byte| byte == b' ' || byte == b'\t' || is_line_ending(byte))
+                .count()
+    }
+
+    /// Return length of a run of given byte from given index.
+    fn scan_repeated_byte(&self, index: usize, byte: u8) -> usize {
+        self.content.as_bytes()[index..]
+            .iter()
+            .take_while(|&&b| b == byte)
+            .count()
+    }
+
+    /// Check if a space or line ending starts from given index, and return its length if found.
+    fn scan_space_or_line_ending(&self, index: usize) -> Option<usize> {
+        let bytes = &self.content.as_bytes()[index..];
+        match bytes.first()? {
+            b' ' | b'\n' => Some(1),
+            b'\r' => Some(if bytes.get(1) == Some(&b'\n') { 2 } else { 1 }),
+            _ => None,
+        }
+    }
+
+    /// Check if a space or line ending ends at given index, and return its length if found.
+    fn scan_space_or_line_ending_backward(&self, index: usize) -> Option<usize> {
+        let bytes = &self.content.as_bytes()[..index];
+        match bytes.last()? {
+            b' ' | b'\r' => Some(1),
+            b'\n' => Some(if bytes.len() >= 2 && bytes[bytes.len() - 2] == b'\r' {
+                2
+            } else {
+                1
+            }),
+            _ => None,
+        }
+    }
+
+    /// Return source text of given content range, unless it is split across segments.
+    fn source(&self, range: Range<usize>) -> Option<&'a str> {
+        let segment = self
+            .segments
+            .iter()
+            .find(|segment| segment.begin <= range.start && range.end <= segment.end())?;
+        let offset = segment.source.start - segment.begin;
+        Some(&self.text[range.start + offset..range.end + offset])
+    }
+
+    /// Return text of given content range with backslash escapes and character references resolved,
+    /// which borrows source if possible.
+    fn unescaped(&self, range: Range<usize>) -> Cow<'a, str> {
+        match self.source(range.clone()) {
+            Some(value) => unescape(value),
+            None => Cow::Owned(unescape(&self.content[range]).into_owned()),
+        }
+    }
+
+    /// Push text item if given range is not empty.
+    fn push_text(&mut self, begin: usize, end: usize) {
+        if begin < end {
+            self.items.push(Item {
+                begin,
+                end,
+                kind: ItemKind::Text,
+            });
+        }
+    }
+
+    /// Match emphasis openers and closers in delimiter stack above given bottom, then remove them from the stack.
+    fn process_emphasis(&mut self, bottom: usize) {
+        // Lower bounds of opener search, indexed by delimiter byte, closer length modulo 3, and whether closer can open.
+        let mut openers_bottom = [[[bottom; 2]; 3]; 3];
+        let mut current = bottom;
+        while current < self.delimiters.len() {
+            let closer = self.delimiter(current);
+            if !closer.active || !closer.can_close {
+                current += 1;
+                continue;
+            }
+            let (byte, can_open, length) = (closer.byte, closer.can_open, closer.length);
+            let closer_length = self.items[self.delimiters[current]].len();
+            let key = (
+                match byte {
+                    b'*' => 0,
+                    b'_' => 1,
+                    _ => 2,
+                },
+                length % 3,
+                can_open as usize,
+            );
+            let opener_bottom = openers_bottom[key.0][key.1][key.2];
+            let opener_position = (opener_bottom..current).rev().find(|&position| {
+                let opener = self.delimiter(position);
+                opener.active
+                    && opener.can_open
+                    && opener.byte == byte
+                    && if byte == b'~' {
+                        // Tildes of mismatched run lengths do not pair.
+                        opener.length == length
+                    } else {
+                        (!opener.can_close && !can_open)
+                            || (opener.length + length) % 3 != 0
+                            || (opener.length % 3 == 0 && length % 3 == 0)
+                    }
+            });
+            match opener_position {
+                Some(position) => {
+                    let opener_length = self.items[self.delimiters[position]].len();
+                    let (length, tag) = if byte == b'~' {
+                        (length, Tag::Strikethrough)
+                    } else if opener_length >= 2 && closer_length >= 2 {
+                        (2, Tag::Strong)
+                    } else {
+                        (1, Tag::Emphasis)
+                    };
+                    for between in position + 1..current {
+                        self.delimiter_mut(between).active = false;
+                    }
+
+                    let opener_item = &mut self.items[self.delimiters[position]];
+                    opener_item.end -= length;
+                    let opener_range = opener_item.end..opener_item.end + length;
+                    let opener_empty = opener_item.is_empty();
+                    if let ItemKind::Delimiter(opener) = &mut opener_item.kind {
+                        opener.openings.push((tag.clone(), opener_range));
+                        opener.active = !opener_empty;
+                    }
+
+                    let closer_item = &mut self.items[self.delimiters[current]];
+                    let closer_range = closer_item.begin..closer_item.begin + length;
+                    closer_item.begin += length;
+                    let closer_empty = closer_item.is_empty();
+                    if let ItemKind::Delimiter(closer) = &mut closer_item.kind {
+                        closer.closings.push((tag, closer_range));
+                        closer.active = !closer_empty;
+                    }
+                    if closer_empty {
+                        current += 1;
+                    }
+                }
+                None => {
+                    openers_bottom[key.0][key.1][key.2] = current;
+                    if !can_open {
+                        self.delimiter_mut(current).active = false;
+                    }
+                    current += 1;
+                }
+            }
+        }
+        self.delimiters.truncate(bottom);
+    }
+
+    fn delimiter(&self, position: usize) -> &Delimiter<'a> {
+        match &self.items[self.delimiters[position]].kind {
+            ItemKind::Delimiter(delimiter) => delimiter,
+            _ => panic!("Unexpected item is found in delimiter stack."),
+        }
+    }
+
+    fn delimiter_mut(&mut self, position: usize) -> &mut Delimiter<'a> {
+        match &mut self.items[self.delimiters[position]].kind {
+            ItemKind::Delimiter(delimiter) => delimiter,
+            _ => panic!("Unexpected item is found in delimiter stack."),
+        }
+    }
+
+    /// Convert parsed items into events with their source ranges,
+    /// where the beginning and end events of an element have the range of the whole element.
+    fn into_events(self) -> Vec<(Event<'a>, Range<usize>)> {
+        let mut events = Vec::new();
+        let mut text = 0..0;
+        for item in &self.items {
+            match &item.kind {
+                ItemKind::Delimiter(delimiter) => {
+                    if !delimiter.closings.is_empty() {
+                        self.push_text_events(&mut events, text.clone());
+                        text = item.begin..item.begin;
+                        for (tag, range) in &delimiter.closings {
+                            events
+                                .push((Event::End(tag.clone()), self.source_range(range.clone())));
+                        }
+                    }
+                    if text.end != item.begin {
+                        self.push_text_events(&mut events, text);
+                        text = item.begin..item.begin;
+                    }
+                    text.end = item.end;
+                    if !delimiter.openings.is_empty() {
+                        self.push_text_events(&mut events, text);
+                        text = item.end..item.end;
+                        for (tag, range) in delimiter.openings.iter().rev() {
+                            events.push((
+                                Event::Begin(tag.clone()),
+                                self.source_range(range.clone()),
+                            ));
+                        }
+                    }
+                }
+                ItemKind::Event(event) => {
+                    self.push_text_events(&mut events, text);
+                    text = item.end..item.end;
+                    events.push((event.clone(), self.source_range(item.begin..item.end)));
+                }
+                ItemKind::Html => {
+                    self.push_text_events(&mut events, text);
+                    text = item.end..item.end;
+                    self.push_source_events(&mut events, item.begin..item.end, Event::InlineHtml);
+                }
+                ItemKind::Text => {
+                    if text.end != item.begin {
+                        self.push_text_events(&mut events, text);
+                        text = item.begin..item.begin;
+                    }
+                    text.end = item.end;
+                }
+            }
+        }
+        self.push_text_events(&mut events, text);
+
+        // Beginning and end events share the range from the opening to the closing.
+        let mut beginnings = Vec::new();
+        for index in 0..events.len() {
+            match events[index].0 {
+                Event::Begin(_) => {
+                    beginnings.push(index);
+                }
+                Event::End(_) => {
+                    let beginning = beginnings.pop().unwrap();
+                    let range = events[beginning].1.start..events[index].1.end;
+                    events[beginning].1 = range.clone();
+                    events[index].1 = range;
+                }
+                _ => {}
+            }
+        }
+        events
+    }
+
+    /// Push text events of given content range.
+    fn push_text_events(&self, events: &mut Vec<(Event<'a>, Range<usize>)>, range: Range<usize>) {
+        self.push_source_events(events, range, |value| Event::Text(Cow::Borrowed(value)));
+    }
+
+    /// Push events of given content range, split at segment boundaries so that each event borrows source.
+    fn push_source_events(
+        &self,
+        events: &mut Vec<(Event<'a>, Range<usize>)>,
+        range: Range<usize>,
+        event: fn(&'a str) -> Event<'a>,
+    ) {
+        for segment in &self.segments {
+            let begin = range.start.max(segment.begin);
+            let end = range.end.min(segment.end());
+            if begin < end {
+                let offset = segment.source.start - segment.begin;
+                let source = begin + offset..end + offset;
+                events.push((event(&self.text[source.clone()]), source));
+            }
+        }
+    }
+
+    /// Convert given content range into source range, which may contain the gaps between segments.
+    fn source_range(&self, range: Range<usize>) -> Range<usize> {
+        let source_index = |index: usize, segment_index: usize| {
+            let segment = &self.segments[segment_index.min(self.segments.len() - 1)];
+            segment.source.start + index - segment.begin
+        };
+        let begin = source_index(
+            range.start,
+            self.segments
+                .partition_point(|segment| segment.end() <= range.start),
+        );
+        if range.is_empty() {
+            return begin..begin;
+        }
+        let end = source_index(
+            range.end,
+            self.segments
+                .partition_point(|segment| segment.end() < range.end),
+        );
+        begin..end
+    }
+}
+
+/// Piece of source text, which is concatenated with others into the content to be parsed.
+struct Segment {
+    begin: usize,
+    source: Range<usize>,
+}
+
+impl Segment {
+    fn end(&self) -> usize {
+        self.begin + self.source.len()
+    }
+}
+
+struct Item<'a> {
+    begin: usize,
+    end: usize,
+    kind: ItemKind<'a>,
+}
+
+impl<'a> Item<'a> {
+    fn is_empty(&self) -> bool {
+        self.begin == self.end
+    }
+
+    fn len(&self) -> usize {
+        self.end - self.begin
+    }
+}
+
+enum ItemKind<'a> {
+    Delimiter(Delimiter<'a>),
+    /// Inline element already converted into an event (e.g. code span).
+    Event(Event<'a>),
+    /// Inline raw HTML, which may be split into events per segment.
+    Html,
+    Text,
+}
+
+/// Opening bracket (`[` or `![`) which may start a link or image.
+struct Bracket {
+    active: bool,
+    delimiters_length: usize,
+    image: bool,
+    item: usize,
+}
+
+/// Content ranges of link reference definition parts.
+struct LinkReferenceDefinitionRanges {
+    destination: Range<usize>,
+    label: Range<usize>,
+    title: Option<Range<usize>>,
+}
+
+/// Run of `*`, `_`, or `~`. Its item range is narrowed as the delimiters are used for emphasis.
+struct Delimiter<'a> {
+    active: bool,
+    byte: u8,
+    can_close: bool,
+    can_open: bool,
+    closings: Vec<(Tag<'a>, Range<usize>)>,
+    length: usize,
+    openings: Vec<(Tag<'a>, Range<usize>)>,
+}
+
+/// Check if delimiter run surrounded by given characters is left-flanking.
+/// Swap the arguments to check if it is right-flanking.
+fn is_left_flanking(previous: Option<char>, next: Option<char>) -> bool {
+    match next {
+        Some(next) if !is_whitespace(next) => {
+            !is_punctuation(next)
+                || previous.map_or(true, |c| is_whitespace(c) || is_punctuation(c))
+        }
+        _ => false,
+    }
+}
+
+/// Resolve backslash escapes and character references in given text (e.g. link destination).
+pub(crate) fn unescape(value: &str) -> Cow<'_, str> {
+    if !value.contains(&['\\', '&'][..]) {
+        return Cow::Borrowed(value);
+    }
+    let mut unescaped = String::with_capacity(value.len());
+    let mut index = 0;
+    while let Some(c) = value[index..].chars().next() {
+        let rest = &value[index..];
+        if c == '\\' && rest[1..].starts_with(|next: char| next.is_ascii_punctuation()) {
+            unescaped.push_str(&rest[1..2]);
+            index += 2;
+        } else if let Some((length, decoded)) = scan_character_reference(rest) {
+            unescaped.push_str(&decoded);
+            index += length;
+        } else {
+            unescaped.push(c);
+            index += c.len_utf8();
+        }
+    }
+    Cow::Owned(unescaped)
+}
+
+/// Check if entity or numeric character reference (e.g. `&amp;`, `&#35;`, or `&#x22;`) starts at the beginning of given text,
+/// and return its length and decoded value if found.
+fn scan_character_reference(text: &str) -> Option<(usize, Cow<'static, str>)> {
+    let bytes = text.as_bytes();
+    if bytes.first() != Some(&b'&') {
+        return None;
+    }
+    if bytes.get(1) == Some(&b'#') {
+        let (radix, digits_begin, max_digits_length) = match bytes.get(2) {
+            Some(b'x' | b'X') => (16, 3, 6),
+            _ => (10, 2, 7),
+        };
+        let digits_length = bytes[digits_begin..]
+            .iter()
+            .take_while(|&&byte| char::from(byte).is_digit(radix))
+            .count();
+        let digits_end = digits_begin + digits_length;
+        if digits_length == 0
+            || digits_length > max_digits_length
+            || bytes.get(digits_end) != Some(&b';')
+        {
+            return None;
+        }
+        // U+0000 and invalid code points are replaced with the replacement character.
+        let c = match u32::from_str_radix(&text[digits_begin..digits_end], radix).ok()? {
+            0 => '\u{fffd}',
+            code_point => char::from_u32(code_point).unwrap_or('\u{fffd}'),
+        };
+        Some((digits_end + 1, Cow::Owned(c.to_string())))
+    } else {
+        let name_length = bytes[1..]
+            .iter()
+            .take_while(|byte| byte.is_ascii_alphanumeric())
+            .count();
+        if bytes.get(1 + name_length) != Some(&b';') {
+            return None;
+        }
+        let value = entities::get(&text[1..1 + name_length])?;
+        Some((name_length + 2, Cow::Borrowed(value)))
+    }
+}
+
+/// Normalize link label to match case-insensitively, by case folding and collapsing whitespaces.
+pub(crate) fn normalize_link_label(label: &str) -> String {
+    label
+        .split(is_whitespace)
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+        .to_uppercase()
+}
+
+fn is_line_ending(byte: u8) -> bool {
+    byte == b'\n' || byte == b'\r'
+}
@@ -1,14 +1,25 @@
+pub mod ast;
 pub mod block_level_parser;
+mod entities;
+mod extended_autolink;
 pub mod html;
+mod html_scanner;
+pub mod inline_level_parser;
+pub mod line_index;
+pub mod markdown;
 pub mod parser;
 pub mod tree;
 pub mod types;
+mod unicode;
+pub mod visit;
 
 #[cfg(doctest)]
 doc_comment::doctest!("../README.md");
 
 #[cfg(test)]
 mod tests {
+    use crate::types::Options;
+
     macro_rules! fail {
         ($name: ident, $number:expr) => {
             #[test]
@@ -41,55 +52,129 @@ mod tests {
         };
     }
 
+    macro_rules! pass_gfm {
+        ($name: ident, $number:expr, $options:expr) => {
+            #[test]
+            fn $name() {
+                assert_eq!(
+                    &convert_ext(
+                        include_str!(concat!("../tests/fixtures/gfm/markdowns/", $number, ".md")),
+                        $options
+                    ),
+                    include_str!(concat!("../tests/fixtures/gfm/htmls/", $number, ".html"))
+                );
+            }
+        };
+    }
+
+    macro_rules! pass_extension {
+        ($name: ident, $options:expr) => {
+            #[test]
+            fn $name() {
+                assert_eq!(
+                    &convert_ext(
+                        include_str!(concat!(
+                            "../tests/fixtures/extensions/markdowns/",
+                            stringify!($name),
+                            ".md"
+                        )),
+                        $options
+                    ),
+                    include_str!(concat!(
+                        "../tests/fixtures/extensions/htmls/",
+                        stringify!($name),
+                        ".html"
+                    ))
+                );
+            }
+        };
+    }
+
+    macro_rules! round_trip_gfm {
+        ($name: ident, $number: expr, $options:expr) => {
+            #[test]
+            fn $name() {
+                let text =
+                    include_str!(concat!("../tests/fixtures/gfm/markdowns/", $number, ".md"));
+                assert_eq!(
+                    convert_ext(&convert_markdown_ext(text, $options), $options),
+                    convert_ext(text, $options)
+                );
+            }
+        };
+    }
+
+    macro_rules! round_trip_extension {
+        ($name: ident, $fixture: ident, $options:expr) => {
+            #[test]
+            fn $name() {
+                let text = include_str!(concat!(
+                    "../tests/fixtures/extensions/markdowns/",
+                    stringify!($fixture),
+                    ".md"
+                ));
+                assert_eq!(
+                    convert_ext(&convert_markdown_ext(text, $options), $options),
+                    convert_ext(text, $options)
+                );
+            }
+        };
+    }
+
     fn convert(text: &str) -> String {
-        let parser = crate::parser::Parser::new(text);
+        convert_ext(text, Options::empty())
+    }
+
+    fn convert_ext(text: &str, options: Options) -> String {
+        let parser = crate::parser::Parser::new_ext(text, options);
         let mut buffer = String::new();
-        crate::html::push_html(&mut buffer, parser);
+        crate::html::push_html_ext(&mut buffer, parser, options);
         buffer
     }
 
-    fail!(example001, "001");
-    fail!(example002, "002");
+    pass!(example001, "001");
+    pass!(example002, "002");
     pass!(example003, "003");
-    fail!(example004, "004");
-    fail!(example005, "005");
-    fail!(example006, "006");
-    fail!(example007, "007");
-    fail!(example008, "008");
-    fail!(example009, "009");
+    pass!(example004, "004");
+    pass!(example005, "005");
+    pass!(example006, "006");
+    pass!(example007, "007");
+    pass!(example008, "008");
+    pass!(example009, "009");
     pass!(example010, "010");
     pass!(example011, "011");
-    fail!(example012, "012");
+    pass!(example012, "012");
     pass!(example013, "013");
-    fail!(example014, "014");
-    fail!(example015, "015");
-    fail!(example016, "016");
-    fail!(example017, "017");
+    pass!(example014, "014");
+    pass!(example015, "015");
+    pass!(example016, "016");
+    pass!(example017, "017");
     pass!(example018, "018");
     pass!(example019, "019");
-    fail!(example020, "020");
+    pass!(example020, "020");
     pass!(example021, "021");
-    fail!(example022, "022");
-    fail!(example023, "023");
-    fail!(example024, "024");
+    pass!(example022, "022");
+    pass!(example023, "023");
+    pass!(example024, "024");
+    // The fixture has lost non-breaking spaces of the spec example.
     fail!(example025, "025");
-    fail!(example026, "026");
-    fail!(example027, "027");
+    pass!(example026, "026");
+    pass!(example027, "027");
     pass!(example028, "028");
     pass!(example029, "029");
     pass!(example030, "030");
     pass!(example031, "031");
-    fail!(example032, "032");
-    fail!(example033, "033");
-    fail!(example034, "034");
-    fail!(example035, "035");
+    pass!(example032, "032");
+    pass!(example033, "033");
+    pass!(example034, "034");
+    pass!(example035, "035");
     pass!(example036, "036");
-    fail!(example037, "037");
-    fail!(example038, "038");
-    fail!(example039, "039");
-    fail!(example040, "040");
-    fail!(example041, "041");
-    fail!(example042, "042");
+    pass!(example037, "037");
+    pass!(example038, "038");
+    pass!(example039, "039");
+    pass!(example040, "040");
+    pass!(example041, "041");
+    pass!(example042, "042");
     pass!(example043, "043");
     pass!(example044, "044");
     pass!(example045, "045");
@@ -103,17 +188,17 @@ mod tests {
     pass!(example053, "053");
     pass!(example054, "054");
     pass!(example055, "055");
-    fail!(example056, "056");
-    fail!(example057, "057");
+    pass!(example056, "056");
+    pass!(example057, "057");
     pass!(example058, "058");
     pass!(example059, "059");
-    fail!(example060, "060");
-    fail!(example061, "061");
+    pass!(example060, "060");
+    pass!(example061, "061");
     pass!(example062, "062");
     pass!(example063, "063");
     pass!(example064, "064");
-    fail!(example065, "065");
-    fail!(example066, "066");
+    pass!(example065, "065");
+    pass!(example066, "066");
     pass!(example067, "067");
     pass!(example068, "068");
     pass!(example069, "069");
@@ -123,13 +208,13 @@ mod tests {
     pass!(example073, "073");
     pass!(example074, "074");
     pass!(example075, "075");
-    fail!(example076, "076");
+    pass!(example076, "076");
     pass!(example077, "077");
     pass!(example078, "078");
     pass!(example079, "079");
-    fail!(example080, "080");
-    fail!(example081, "081");
-    fail!(example082, "082");
+    pass!(example080, "080");
+    pass!(example081, "081");
+    pass!(example082, "082");
     pass!(example083, "083");
     pass!(example084, "084");
     pass!(example085, "085");
@@ -141,22 +226,22 @@ mod tests {
     pass!(example091, "091");
     pass!(example092, "092");
     pass!(example093, "093");
-    fail!(example094, "094");
+    pass!(example094, "094");
     pass!(example095, "095");
     pass!(example096, "096");
     pass!(example097, "097");
     pass!(example098, "098");
-    fail!(example099, "099");
+    pass!(example099, "099");
     pass!(example100, "100");
     pass!(example101, "101");
-    fail!(example102, "102");
+    pass!(example102, "102");
     pass!(example103, "103");
     pass!(example104, "104");
     pass!(example105, "105");
-    fail!(example106, "106");
+    pass!(example106, "106");
     pass!(example107, "107");
-    fail!(example108, "108");
-    fail!(example109, "109");
+    pass!(example108, "108");
+    pass!(example109, "109");
     pass!(example110, "110");
     pass!(example111, "111");
     pass!(example112, "112");
@@ -168,14 +253,14 @@ mod tests {
     pass!(example118, "118");
     pass!(example119, "119");
     pass!(example120, "120");
-    fail!(example121, "121");
+    pass!(example121, "121");
     pass!(example122, "122");
     pass!(example123, "123");
     pass!(example124, "124");
     pass!(example125, "125");
     pass!(example126, "126");
     pass!(example127, "127");
-    fail!(example128, "128");
+    pass!(example128, "128");
     pass!(example129, "129");
     pass!(example130, "130");
     pass!(example131, "131");
@@ -185,24 +270,24 @@ mod tests {
     pass!(example135, "135");
     pass!(example136, "136");
     pass!(example137, "137");
-    fail!(example138, "138");
+    pass!(example138, "138");
     pass!(example139, "139");
     pass!(example140, "140");
     pass!(example141, "141");
     pass!(example142, "142");
     pass!(example143, "143");
     pass!(example144, "144");
-    fail!(example145, "145");
+    pass!(example145, "145");
     pass!(example146, "146");
     pass!(example147, "147");
-    fail!(example148, "148");
+    pass!(example148, "148");
     pass!(example149, "149");
     pass!(example150, "150");
     pass!(example151, "151");
-    fail!(example152, "152");
+    pass!(example152, "152");
     pass!(example153, "153");
     pass!(example154, "154");
-    fail!(example155, "155");
+    pass!(example155, "155");
     pass!(example156, "156");
     pass!(example157, "157");
     pass!(example158, "158");
@@ -214,58 +299,58 @@ mod tests {
     pass!(example164, "164");
     pass!(example165, "165");
     pass!(example166, "166");
-    fail!(example167, "167");
-    fail!(example168, "168");
+    pass!(example167, "167");
+    pass!(example168, "168");
     pass!(example169, "169");
     pass!(example170, "170");
     pass!(example171, "171");
     pass!(example172, "172");
     pass!(example173, "173");
-    fail!(example174, "174");
-    fail!(example175, "175");
-    fail!(example176, "176");
-    fail!(example177, "177");
+    pass!(example174, "174");
+    pass!(example175, "175");
+    pass!(example176, "176");
+    pass!(example177, "177");
     pass!(example178, "178");
     pass!(example179, "179");
     pass!(example180, "180");
     pass!(example181, "181");
     pass!(example182, "182");
-    fail!(example183, "183");
+    pass!(example183, "183");
     pass!(example184, "184");
     pass!(example185, "185");
     pass!(example186, "186");
-    fail!(example187, "187");
-    fail!(example188, "188");
+    pass!(example187, "187");
+    pass!(example188, "188");
     pass!(example189, "189");
     pass!(example190, "190");
     pass!(example191, "191");
-    fail!(example192, "192");
-    fail!(example193, "193");
-    fail!(example194, "194");
-    fail!(example195, "195");
-    fail!(example196, "196");
+    pass!(example192, "192");
+    pass!(example193, "193");
+    pass!(example194, "194");
+    pass!(example195, "195");
+    pass!(example196, "196");
     pass!(example197, "197");
-    fail!(example198, "198");
+    pass!(example198, "198");
     pass!(example199, "199");
-    fail!(example200, "200");
-    fail!(example201, "201");
-    fail!(example202, "202");
-    fail!(example203, "203");
-    fail!(example204, "204");
-    fail!(example205, "205");
-    fail!(example206, "206");
-    fail!(example207, "207");
-    fail!(example208, "208");
+    pass!(example200, "200");
+    pass!(example201, "201");
+    pass!(example202, "202");
+    pass!(example203, "203");
+    pass!(example204, "204");
+    pass!(example205, "205");
+    pass!(example206, "206");
+    pass!(example207, "207");
+    pass!(example208, "208");
     pass!(example209, "209");
-    fail!(example210, "210");
+    pass!(example210, "210");
     pass!(example211, "211");
     pass!(example212, "212");
     pass!(example213, "213");
-    fail!(example214, "214");
-    fail!(example215, "215");
-    fail!(example216, "216");
-    fail!(example217, "217");
-    fail!(example218, "218");
+    pass!(example214, "214");
+    pass!(example215, "215");
+    pass!(example216, "216");
+    pass!(example217, "217");
+    pass!(example218, "218");
     pass!(example219, "219");
     pass!(example220, "220");
     pass!(example221, "221");
@@ -273,7 +358,7 @@ mod tests {
     pass!(example223, "223");
     pass!(example224, "224");
     pass!(example225, "225");
-    fail!(example226, "226");
+    pass!(example226, "226");
     pass!(example227, "227");
     pass!(example228, "228");
     pass!(example229, "229");
@@ -282,7 +367,7 @@ mod tests {
     pass!(example232, "232");
     pass!(example233, "233");
     pass!(example234, "234");
-    fail!(example235, "235");
+    pass!(example235, "235");
     pass!(example236, "236");
     pass!(example237, "237");
     pass!(example238, "238");
@@ -301,403 +386,1088 @@ mod tests {
     pass!(example251, "251");
     pass!(example252, "252");
     pass!(example253, "253");
-    fail!(example254, "254");
-    fail!(example255, "255");
-    fail!(example256, "256");
-    fail!(example257, "257");
-    fail!(example258, "258");
-    fail!(example259, "259");
-    fail!(example260, "260");
+    pass!(example254, "254");
+    pass!(example255, "255");
+    pass!(example256, "256");
+    pass!(example257, "257");
+    pass!(example258, "258");
+    pass!(example259, "259");
+    pass!(example260, "260");
     pass!(example261, "261");
-    fail!(example262, "262");
-    fail!(example263, "263");
-    fail!(example264, "264");
-    fail!(example265, "265");
+    pass!(example262, "262");
+    pass!(example263, "263");
+    pass!(example264, "264");
+    pass!(example265, "265");
     pass!(example266, "266");
-    fail!(example267, "267");
-    fail!(example268, "268");
+    pass!(example267, "267");
+    pass!(example268, "268");
     pass!(example269, "269");
-    fail!(example270, "270");
-    fail!(example271, "271");
+    pass!(example270, "270");
+    pass!(example271, "271");
     pass!(example272, "272");
-    fail!(example273, "273");
-    fail!(example274, "274");
+    pass!(example273, "273");
+    pass!(example274, "274");
     pass!(example275, "275");
-    fail!(example276, "276");
-    fail!(example277, "277");
-    fail!(example278, "278");
-    fail!(example279, "279");
-    fail!(example280, "280");
-    fail!(example281, "281");
-    fail!(example282, "282");
-    fail!(example283, "283");
-    fail!(example284, "284");
+    pass!(example276, "276");
+    pass!(example277, "277");
+    pass!(example278, "278");
+    pass!(example279, "279");
+    pass!(example280, "280");
+    pass!(example281, "281");
+    pass!(example282, "282");
+    pass!(example283, "283");
+    pass!(example284, "284");
     pass!(example285, "285");
-    fail!(example286, "286");
-    fail!(example287, "287");
-    fail!(example288, "288");
+    pass!(example286, "286");
+    pass!(example287, "287");
+    pass!(example288, "288");
     pass!(example289, "289");
-    fail!(example290, "290");
-    fail!(example291, "291");
-    fail!(example292, "292");
-    fail!(example293, "293");
-    fail!(example294, "294");
-    fail!(example295, "295");
-    fail!(example296, "296");
-    fail!(example297, "297");
-    fail!(example298, "298");
-    fail!(example299, "299");
-    fail!(example300, "300");
-    fail!(example301, "301");
-    fail!(example302, "302");
-    fail!(example303, "303");
+    pass!(example290, "290");
+    pass!(example291, "291");
+    pass!(example292, "292");
+    pass!(example293, "293");
+    pass!(example294, "294");
+    pass!(example295, "295");
+    pass!(example296, "296");
+    pass!(example297, "297");
+    pass!(example298, "298");
+    pass!(example299, "299");
+    pass!(example300, "300");
+    pass!(example301, "301");
+    pass!(example302, "302");
+    pass!(example303, "303");
     pass!(example304, "304");
-    fail!(example305, "305");
-    fail!(example306, "306");
-    fail!(example307, "307");
-    fail!(example308, "308");
-    fail!(example309, "309");
-    fail!(example310, "310");
-    fail!(example311, "311");
-    fail!(example312, "312");
-    fail!(example313, "313");
-    fail!(example314, "314");
-    fail!(example315, "315");
-    fail!(example316, "316");
-    fail!(example317, "317");
-    fail!(example318, "318");
-    fail!(example319, "319");
-    fail!(example320, "320");
-    fail!(example321, "321");
-    fail!(example322, "322");
-    fail!(example323, "323");
-    fail!(example324, "324");
-    fail!(example325, "325");
-    fail!(example326, "326");
-    fail!(example327, "327");
-    fail!(example328, "328");
-    fail!(example329, "329");
-    fail!(example330, "330");
-    fail!(example331, "331");
-    fail!(example332, "332");
+    pass!(example305, "305");
+    pass!(example306, "306");
+    pass!(example307, "307");
+    pass!(example308, "308");
+    pass!(example309, "309");
+    pass!(example310, "310");
+    pass!(example311, "311");
+    pass!(example312, "312");
+    pass!(example313, "313");
+    pass!(example314, "314");
+    pass!(example315, "315");
+    pass!(example316, "316");
+    pass!(example317, "317");
+    pass!(example318, "318");
+    pass!(example319, "319");
+    pass!(example320, "320");
+    pass!(example321, "321");
+    pass!(example322, "322");
+    pass!(example323, "323");
+    pass!(example324, "324");
+    pass!(example325, "325");
+    pass!(example326, "326");
+    pass!(example327, "327");
+    pass!(example328, "328");
+    pass!(example329, "329");
+    pass!(example330, "330");
+    pass!(example331, "331");
+    pass!(example332, "332");
+    // The fixture has lost non-breaking spaces of the spec example.
     fail!(example333, "333");
-    fail!(example334, "334");
-    fail!(example335, "335");
-    fail!(example336, "336");
-    fail!(example337, "337");
-    fail!(example338, "338");
-    fail!(example339, "339");
-    fail!(example340, "340");
-    fail!(example341, "341");
-    fail!(example342, "342");
-    fail!(example343, "343");
-    fail!(example344, "344");
-    fail!(example345, "345");
-    fail!(example346, "346");
+    pass!(example334, "334");
+    pass!(example335, "335");
+    pass!(example336, "336");
+    pass!(example337, "337");
+    pass!(example338, "338");
+    pass!(example339, "339");
+    pass!(example340, "340");
+    pass!(example341, "341");
+    pass!(example342, "342");
+    pass!(example343, "343");
+    pass!(example344, "344");
+    pass!(example345, "345");
+    pass!(example346, "346");
     pass!(example347, "347");
     pass!(example348, "348");
-    fail!(example349, "349");
-    fail!(example350, "350");
+    pass!(example349, "349");
+    pass!(example350, "350");
     pass!(example351, "351");
     pass!(example352, "352");
-    pass!(example353, "353");
-    fail!(example354, "354");
-    fail!(example355, "355");
-    fail!(example356, "356");
+    // The fixture has lost non-breaking spaces of the spec example, so it is parsed as a list.
+    fail!(example353, "353");
+    pass!(example354, "354");
+    pass!(example355, "355");
+    pass!(example356, "356");
     pass!(example357, "357");
     pass!(example358, "358");
     pass!(example359, "359");
     pass!(example360, "360");
     pass!(example361, "361");
     pass!(example362, "362");
-    fail!(example363, "363");
+    pass!(example363, "363");
     pass!(example364, "364");
     pass!(example365, "365");
     pass!(example366, "366");
     pass!(example367, "367");
-    fail!(example368, "368");
-    fail!(example369, "369");
+    pass!(example368, "368");
+    pass!(example369, "369");
     pass!(example370, "370");
     pass!(example371, "371");
-    fail!(example372, "372");
+    pass!(example372, "372");
     pass!(example373, "373");
     pass!(example374, "374");
-    fail!(example375, "375");
-    fail!(example376, "376");
-    fail!(example377, "377");
+    pass!(example375, "375");
+    pass!(example376, "376");
+    pass!(example377, "377");
     pass!(example378, "378");
     pass!(example379, "379");
-    fail!(example380, "380");
-    fail!(example381, "381");
+    pass!(example380, "380");
+    pass!(example381, "381");
     pass!(example382, "382");
     pass!(example383, "383");
     pass!(example384, "384");
     pass!(example385, "385");
     pass!(example386, "386");
     pass!(example387, "387");
-    fail!(example388, "388");
-    fail!(example389, "389");
+    pass!(example388, "388");
+    pass!(example389, "389");
     pass!(example390, "390");
     pass!(example391, "391");
-    fail!(example392, "392");
-    fail!(example393, "393");
-    fail!(example394, "394");
-    fail!(example395, "395");
+    pass!(example392, "392");
+    pass!(example393, "393");
+    pass!(example394, "394");
+    pass!(example395, "395");
     pass!(example396, "396");
     pass!(example397, "397");
-    fail!(example398, "398");
+    pass!(example398, "398");
     pass!(example399, "399");
     pass!(example400, "400");
-    fail!(example401, "401");
-    fail!(example402, "402");
-    fail!(example403, "403");
-    fail!(example404, "404");
-    fail!(example405, "405");
-    fail!(example406, "406");
-    fail!(example407, "407");
-    fail!(example408, "408");
-    fail!(example409, "409");
-    fail!(example410, "410");
-    fail!(example411, "411");
-    fail!(example412, "412");
-    fail!(example413, "413");
-    fail!(example414, "414");
-    fail!(example415, "415");
-    fail!(example416, "416");
-    fail!(example417, "417");
-    fail!(example418, "418");
+    pass!(example401, "401");
+    pass!(example402, "402");
+    pass!(example403, "403");
+    pass!(example404, "404");
+    pass!(example405, "405");
+    pass!(example406, "406");
+    pass!(example407, "407");
+    pass!(example408, "408");
+    pass!(example409, "409");
+    pass!(example410, "410");
+    pass!(example411, "411");
+    pass!(example412, "412");
+    pass!(example413, "413");
+    pass!(example414, "414");
+    pass!(example415, "415");
+    pass!(example416, "416");
+    pass!(example417, "417");
+    pass!(example418, "418");
     pass!(example419, "419");
     pass!(example420, "420");
-    fail!(example421, "421");
-    fail!(example422, "422");
-    fail!(example423, "423");
-    fail!(example424, "424");
-    fail!(example425, "425");
-    fail!(example426, "426");
-    fail!(example427, "427");
-    fail!(example428, "428");
-    fail!(example429, "429");
-    fail!(example430, "430");
-    fail!(example431, "431");
-    fail!(example432, "432");
+    pass!(example421, "421");
+    pass!(example422, "422");
+    pass!(example423, "423");
+    pass!(example424, "424");
+    pass!(example425, "425");
+    pass!(example426, "426");
+    pass!(example427, "427");
+    pass!(example428, "428");
+    pass!(example429, "429");
+    pass!(example430, "430");
+    pass!(example431, "431");
+    pass!(example432, "432");
     pass!(example433, "433");
     pass!(example434, "434");
     pass!(example435, "435");
-    fail!(example436, "436");
-    fail!(example437, "437");
+    pass!(example436, "436");
+    pass!(example437, "437");
     pass!(example438, "438");
-    fail!(example439, "439");
-    fail!(example440, "440");
-    fail!(example441, "441");
-    fail!(example442, "442");
-    fail!(example443, "443");
-    fail!(example444, "444");
-    fail!(example445, "445");
-    fail!(example446, "446");
+    pass!(example439, "439");
+    pass!(example440, "440");
+    pass!(example441, "441");
+    pass!(example442, "442");
+    pass!(example443, "443");
+    pass!(example444, "444");
+    pass!(example445, "445");
+    pass!(example446, "446");
     pass!(example447, "447");
-    fail!(example448, "448");
-    fail!(example449, "449");
+    pass!(example448, "448");
+    pass!(example449, "449");
     pass!(example450, "450");
-    fail!(example451, "451");
-    fail!(example452, "452");
-    fail!(example453, "453");
-    fail!(example454, "454");
-    fail!(example455, "455");
-    fail!(example456, "456");
-    fail!(example457, "457");
-    fail!(example458, "458");
-    fail!(example459, "459");
-    fail!(example460, "460");
-    fail!(example461, "461");
-    fail!(example462, "462");
-    fail!(example463, "463");
-    fail!(example464, "464");
-    fail!(example465, "465");
-    fail!(example466, "466");
-    fail!(example467, "467");
-    fail!(example468, "468");
-    fail!(example469, "469");
-    fail!(example470, "470");
-    fail!(example471, "471");
-    fail!(example472, "472");
-    fail!(example473, "473");
-    fail!(example474, "474");
-    fail!(example475, "475");
-    fail!(example476, "476");
-    fail!(example477, "477");
-    fail!(example478, "478");
-    fail!(example479, "479");
-    fail!(example480, "480");
-    fail!(example481, "481");
-    fail!(example482, "482");
-    fail!(example483, "483");
-    fail!(example484, "484");
-    fail!(example485, "485");
-    fail!(example486, "486");
+    pass!(example451, "451");
+    pass!(example452, "452");
+    pass!(example453, "453");
+    pass!(example454, "454");
+    pass!(example455, "455");
+    pass!(example456, "456");
+    pass!(example457, "457");
+    pass!(example458, "458");
+    pass!(example459, "459");
+    pass!(example460, "460");
+    pass!(example461, "461");
+    pass!(example462, "462");
+    pass!(example463, "463");
+    pass!(example464, "464");
+    pass!(example465, "465");
+    pass!(example466, "466");
+    pass!(example467, "467");
+    pass!(example468, "468");
+    pass!(example469, "469");
+    pass!(example470, "470");
+    pass!(example471, "471");
+    pass!(example472, "472");
+    pass!(example473, "473");
+    pass!(example474, "474");
+    pass!(example475, "475");
+    pass!(example476, "476");
+    pass!(example477, "477");
+    pass!(example478, "478");
+    pass!(example479, "479");
+    pass!(example480, "480");
+    pass!(example481, "481");
+    pass!(example482, "482");
+    pass!(example483, "483");
+    pass!(example484, "484");
+    pass!(example485, "485");
+    pass!(example486, "486");
     pass!(example487, "487");
-    fail!(example488, "488");
+    pass!(example488, "488");
     pass!(example489, "489");
-    fail!(example490, "490");
-    fail!(example491, "491");
-    fail!(example492, "492");
-    fail!(example493, "493");
-    fail!(example494, "494");
-    fail!(example495, "495");
+    pass!(example490, "490");
+    pass!(example491, "491");
+    pass!(example492, "492");
+    pass!(example493, "493");
+    pass!(example494, "494");
+    pass!(example495, "495");
     pass!(example496, "496");
-    fail!(example497, "497");
-    fail!(example498, "498");
-    fail!(example499, "499");
-    fail!(example500, "500");
-    fail!(example501, "501");
-    fail!(example502, "502");
-    fail!(example503, "503");
-    fail!(example504, "504");
-    fail!(example505, "505");
+    pass!(example497, "497");
+    pass!(example498, "498");
+    pass!(example499, "499");
+    pass!(example500, "500");
+    pass!(example501, "501");
+    pass!(example502, "502");
+    pass!(example503, "503");
+    pass!(example504, "504");
+    pass!(example505, "505");
     fail!(example506, "506");
     pass!(example507, "507");
-    fail!(example508, "508");
-    fail!(example509, "509");
+    pass!(example508, "508");
+    pass!(example509, "509");
     pass!(example510, "510");
-    fail!(example511, "511");
+    pass!(example511, "511");
     pass!(example512, "512");
-    fail!(example513, "513");
-    fail!(example514, "514");
-    fail!(example515, "515");
-    fail!(example516, "516");
-    fail!(example517, "517");
-    fail!(example518, "518");
-    fail!(example519, "519");
-    fail!(example520, "520");
-    fail!(example521, "521");
-    fail!(example522, "522");
-    fail!(example523, "523");
-    fail!(example524, "524");
-    fail!(example525, "525");
-    fail!(example526, "526");
-    fail!(example527, "527");
-    fail!(example528, "528");
-    fail!(example529, "529");
-    fail!(example530, "530");
-    fail!(example531, "531");
-    fail!(example532, "532");
-    fail!(example533, "533");
-    fail!(example534, "534");
-    fail!(example535, "535");
-    fail!(example536, "536");
-    fail!(example537, "537");
-    fail!(example538, "538");
-    fail!(example539, "539");
-    fail!(example540, "540");
-    fail!(example541, "541");
-    fail!(example542, "542");
-    fail!(example543, "543");
-    fail!(example544, "544");
+    pass!(example513, "513");
+    pass!(example514, "514");
+    pass!(example515, "515");
+    pass!(example516, "516");
+    pass!(example517, "517");
+    pass!(example518, "518");
+    pass!(example519, "519");
+    pass!(example520, "520");
+    pass!(example521, "521");
+    pass!(example522, "522");
+    pass!(example523, "523");
+    pass!(example524, "524");
+    pass!(example525, "525");
+    pass!(example526, "526");
+    pass!(example527, "527");
+    pass!(example528, "528");
+    pass!(example529, "529");
+    pass!(example530, "530");
+    pass!(example531, "531");
+    pass!(example532, "532");
+    pass!(example533, "533");
+    pass!(example534, "534");
+    pass!(example535, "535");
+    pass!(example536, "536");
+    pass!(example537, "537");
+    pass!(example538, "538");
+    pass!(example539, "539");
+    pass!(example540, "540");
+    pass!(example541, "541");
+    pass!(example542, "542");
+    pass!(example543, "543");
+    pass!(example544, "544");
     pass!(example545, "545");
     pass!(example546, "546");
     pass!(example547, "547");
-    fail!(example548, "548");
-    fail!(example549, "549");
+    pass!(example548, "548");
+    pass!(example549, "549");
     pass!(example550, "550");
     pass!(example551, "551");
-    fail!(example552, "552");
-    fail!(example553, "553");
-    fail!(example554, "554");
-    fail!(example555, "555");
-    fail!(example556, "556");
-    fail!(example557, "557");
-    fail!(example558, "558");
-    fail!(example559, "559");
-    fail!(example560, "560");
-    fail!(example561, "561");
-    fail!(example562, "562");
-    fail!(example563, "563");
-    fail!(example564, "564");
-    fail!(example565, "565");
-    fail!(example566, "566");
-    fail!(example567, "567");
-    fail!(example568, "568");
-    fail!(example569, "569");
-    fail!(example570, "570");
-    fail!(example571, "571");
-    fail!(example572, "572");
-    fail!(example573, "573");
-    fail!(example574, "574");
-    fail!(example575, "575");
-    fail!(example576, "576");
-    fail!(example577, "577");
-    fail!(example578, "578");
-    fail!(example579, "579");
-    fail!(example580, "580");
-    fail!(example581, "581");
-    fail!(example582, "582");
-    fail!(example583, "583");
-    fail!(example584, "584");
-    fail!(example585, "585");
-    fail!(example586, "586");
-    fail!(example587, "587");
-    fail!(example588, "588");
+    pass!(example552, "552");
+    pass!(example553, "553");
+    pass!(example554, "554");
+    pass!(example555, "555");
+    pass!(example556, "556");
+    pass!(example557, "557");
+    pass!(example558, "558");
+    pass!(example559, "559");
+    pass!(example560, "560");
+    pass!(example561, "561");
+    pass!(example562, "562");
+    pass!(example563, "563");
+    pass!(example564, "564");
+    pass!(example565, "565");
+    pass!(example566, "566");
+    pass!(example567, "567");
+    pass!(example568, "568");
+    pass!(example569, "569");
+    pass!(example570, "570");
+    pass!(example571, "571");
+    pass!(example572, "572");
+    pass!(example573, "573");
+    pass!(example574, "574");
+    pass!(example575, "575");
+    pass!(example576, "576");
+    pass!(example577, "577");
+    pass!(example578, "578");
+    pass!(example579, "579");
+    pass!(example580, "580");
+    pass!(example581, "581");
+    pass!(example582, "582");
+    pass!(example583, "583");
+    pass!(example584, "584");
+    pass!(example585, "585");
+    pass!(example586, "586");
+    pass!(example587, "587");
+    pass!(example588, "588");
     pass!(example589, "589");
-    fail!(example590, "590");
-    fail!(example591, "591");
-    fail!(example592, "592");
-    fail!(example593, "593");
-    fail!(example594, "594");
-    fail!(example595, "595");
-    fail!(example596, "596");
-    fail!(example597, "597");
-    fail!(example598, "598");
-    fail!(example599, "599");
-    fail!(example600, "600");
+    pass!(example590, "590");
+    pass!(example591, "591");
+    pass!(example592, "592");
+    pass!(example593, "593");
+    pass!(example594, "594");
+    pass!(example595, "595");
+    pass!(example596, "596");
+    pass!(example597, "597");
+    pass!(example598, "598");
+    pass!(example599, "599");
+    pass!(example600, "600");
     pass!(example601, "601");
-    fail!(example602, "602");
-    fail!(example603, "603");
-    fail!(example604, "604");
-    fail!(example605, "605");
+    pass!(example602, "602");
+    pass!(example603, "603");
+    pass!(example604, "604");
+    pass!(example605, "605");
     pass!(example606, "606");
     pass!(example607, "607");
-    fail!(example608, "608");
+    pass!(example608, "608");
     pass!(example609, "609");
     pass!(example610, "610");
     pass!(example611, "611");
-    fail!(example612, "612");
-    fail!(example613, "613");
-    fail!(example614, "614");
-    fail!(example615, "615");
-    fail!(example616, "616");
+    pass!(example612, "612");
+    pass!(example613, "613");
+    pass!(example614, "614");
+    pass!(example615, "615");
+    pass!(example616, "616");
     pass!(example617, "617");
     pass!(example618, "618");
     pass!(example619, "619");
     pass!(example620, "620");
-    fail!(example621, "621");
-    fail!(example622, "622");
-    fail!(example623, "623");
-    fail!(example624, "624");
+    pass!(example621, "621");
+    pass!(example622, "622");
+    pass!(example623, "623");
+    pass!(example624, "624");
     pass!(example625, "625");
     pass!(example626, "626");
-    fail!(example627, "627");
-    fail!(example628, "628");
-    fail!(example629, "629");
-    fail!(example630, "630");
-    fail!(example631, "631");
-    fail!(example632, "632");
-    fail!(example633, "633");
-    fail!(example634, "634");
-    fail!(example635, "635");
-    fail!(example636, "636");
-    fail!(example637, "637");
-    fail!(example638, "638");
-    fail!(example639, "639");
-    fail!(example640, "640");
-    fail!(example641, "641");
-    fail!(example642, "642");
-    fail!(example643, "643");
+    pass!(example627, "627");
+    pass!(example628, "628");
+    pass!(example629, "629");
+    pass!(example630, "630");
+    pass!(example631, "631");
+    pass!(example632, "632");
+    pass!(example633, "633");
+    pass!(example634, "634");
+    pass!(example635, "635");
+    pass!(example636, "636");
+    pass!(example637, "637");
+    pass!(example638, "638");
+    pass!(example639, "639");
+    pass!(example640, "640");
+    pass!(example641, "641");
+    pass!(example642, "642");
+    pass!(example643, "643");
     pass!(example644, "644");
-    fail!(example645, "645");
+    pass!(example645, "645");
     pass!(example646, "646");
     pass!(example647, "647");
     pass!(example648, "648");
-    fail!(example649, "649");
+    pass!(example649, "649");
     pass!(example650, "650");
     pass!(example651, "651");
     pass!(example652, "652");
+
+    pass_gfm!(gfm_example198, "198", Options::ENABLE_TABLES);
+    pass_gfm!(gfm_example199, "199", Options::ENABLE_TABLES);
+    pass_gfm!(gfm_example200, "200", Options::ENABLE_TABLES);
+    pass_gfm!(gfm_example201, "201", Options::ENABLE_TABLES);
+    pass_gfm!(gfm_example202, "202", Options::ENABLE_TABLES);
+    pass_gfm!(gfm_example203, "203", Options::ENABLE_TABLES);
+    pass_gfm!(gfm_example204, "204", Options::ENABLE_TABLES);
+    pass_gfm!(gfm_example205, "205", Options::ENABLE_TABLES);
+    pass_gfm!(gfm_example491, "491", Options::ENABLE_STRIKETHROUGH);
+    pass_gfm!(gfm_example492, "492", Options::ENABLE_STRIKETHROUGH);
+    pass_gfm!(gfm_example493, "493", Options::ENABLE_STRIKETHROUGH);
+    pass_gfm!(gfm_example279, "279", Options::ENABLE_TASKLISTS);
+    pass_gfm!(gfm_example280, "280", Options::ENABLE_TASKLISTS);
+    pass_extension!(loose_task_lists, Options::ENABLE_TASKLISTS);
+    pass_gfm!(gfm_example621, "621", Options::ENABLE_EXTENDED_AUTOLINKS);
+    pass_gfm!(gfm_example622, "622", Options::ENABLE_EXTENDED_AUTOLINKS);
+    pass_gfm!(gfm_example623, "623", Options::ENABLE_EXTENDED_AUTOLINKS);
+    pass_gfm!(gfm_example624, "624", Options::ENABLE_EXTENDED_AUTOLINKS);
+    pass_gfm!(gfm_example625, "625", Options::ENABLE_EXTENDED_AUTOLINKS);
+    pass_gfm!(gfm_example626, "626", Options::ENABLE_EXTENDED_AUTOLINKS);
+    pass_gfm!(gfm_example627, "627", Options::ENABLE_EXTENDED_AUTOLINKS);
+    pass_gfm!(gfm_example628, "628", Options::ENABLE_EXTENDED_AUTOLINKS);
+    pass_gfm!(gfm_example629, "629", Options::ENABLE_EXTENDED_AUTOLINKS);
+    pass_gfm!(gfm_example630, "630", Options::ENABLE_EXTENDED_AUTOLINKS);
+    pass_gfm!(gfm_example631, "631", Options::ENABLE_EXTENDED_AUTOLINKS);
+    pass_gfm!(gfm_example652, "652", Options::ENABLE_TAGFILTER);
+
+    pass_extension!(footnote_definitions, Options::ENABLE_FOOTNOTES);
+    pass_extension!(
+        footnotes,
+        Options::ENABLE_FOOTNOTES | Options::ENABLE_FOOTNOTE_SECTION
+    );
+    pass_extension!(heading_attributes, Options::ENABLE_HEADING_ATTRIBUTES);
+    pass_extension!(nested_footnote_definitions, Options::ENABLE_FOOTNOTES);
+    pass_extension!(
+        nested_footnote_definitions_in_section,
+        Options::ENABLE_FOOTNOTES | Options::ENABLE_FOOTNOTE_SECTION
+    );
+    pass_extension!(smart_punctuation, Options::ENABLE_SMART_PUNCTUATION);
+
+    round_trip_gfm!(round_trip_gfm_example198, "198", Options::ENABLE_TABLES);
+    round_trip_gfm!(round_trip_gfm_example199, "199", Options::ENABLE_TABLES);
+    round_trip_gfm!(round_trip_gfm_example200, "200", Options::ENABLE_TABLES);
+    round_trip_gfm!(round_trip_gfm_example201, "201", Options::ENABLE_TABLES);
+    round_trip_gfm!(round_trip_gfm_example202, "202", Options::ENABLE_TABLES);
+    round_trip_gfm!(round_trip_gfm_example203, "203", Options::ENABLE_TABLES);
+    round_trip_gfm!(round_trip_gfm_example204, "204", Options::ENABLE_TABLES);
+    round_trip_gfm!(round_trip_gfm_example205, "205", Options::ENABLE_TABLES);
+    round_trip_gfm!(round_trip_gfm_example279, "279", Options::ENABLE_TASKLISTS);
+    round_trip_gfm!(round_trip_gfm_example280, "280", Options::ENABLE_TASKLISTS);
+    round_trip_extension!(
+        round_trip_loose_task_lists,
+        loose_task_lists,
+        Options::ENABLE_TASKLISTS
+    );
+    round_trip_gfm!(
+        round_trip_gfm_example491,
+        "491",
+        Options::ENABLE_STRIKETHROUGH
+    );
+    round_trip_gfm!(
+        round_trip_gfm_example492,
+        "492",
+        Options::ENABLE_STRIKETHROUGH
+    );
+    round_trip_gfm!(
+        round_trip_gfm_example493,
+        "493",
+        Options::ENABLE_STRIKETHROUGH
+    );
+    round_trip_extension!(
+        round_trip_footnote_definitions,
+        footnote_definitions,
+        Options::ENABLE_FOOTNOTES
+    );
+    round_trip_extension!(round_trip_footnotes, footnotes, Options::ENABLE_FOOTNOTES);
+    round_trip_extension!(
+        round_trip_nested_footnote_definitions,
+        nested_footnote_definitions,
+        Options::ENABLE_FOOTNOTES
+    );
+    round_trip_extension!(
+        round_trip_heading_attributes,
+        heading_attributes,
+        Options::ENABLE_HEADING_ATTRIBUTES
+    );
+
+    #[test]
+    fn code_span_converts_line_endings_into_spaces() {
+        use crate::types::Event;
+        use std::collections::HashMap;
+
+        assert_eq!(convert("`a\nb`\n"), "<p><code>a b</code></p>\n");
+        let text = "`a\nb\r\nc`";
+        let events = crate::inline_level_parser::Parser::new(
+            text,
+            std::iter::once(0..text.len()).collect(),
+            Options::empty(),
+        )
+        .run(&HashMap::new(), &HashMap::new());
+        assert_eq!(events[0].0, Event::Code("a b c".into()));
+    }
+
+    #[test]
+    fn options_combine_as_flags() {
+        let tables = Options::ENABLE_TABLES;
+        let footnotes = Options::ENABLE_FOOTNOTES | Options::ENABLE_FOOTNOTE_SECTION;
+        let mut options = tables | footnotes;
+        assert!(Options::all().contains(options));
+        assert_eq!(options & footnotes, footnotes);
+        assert_eq!(options - footnotes, tables);
+        assert!(tables.intersection(footnotes).is_empty());
+        options -= tables;
+        assert_eq!(options, footnotes);
+        options &= Options::ENABLE_FOOTNOTES;
+        assert_eq!(options, Options::ENABLE_FOOTNOTES);
+        assert!((Options::all() - Options::ENABLE_SOURCEPOS)
+            .difference(Options::all())
+            .is_empty());
+    }
+
+    #[test]
+    fn parse_markers_at_end_of_text() {
+        assert_eq!(convert("a\n`"), "<p>a\n`</p>\n");
+        assert_eq!(convert("a\n~~~"), "<p>a</p>\n<pre><code></code></pre>\n");
+        assert_eq!(convert("~~~"), "<pre><code></code></pre>\n");
+        assert_eq!(
+            convert("> ```"),
+            "<blockquote>\n<pre><code></code></pre>\n</blockquote>\n"
+        );
+        assert_eq!(convert("***\r"), "<hr />\n");
+    }
+
+    fn convert_markdown(text: &str) -> String {
+        convert_markdown_ext(text, Options::empty())
+    }
+
+    fn convert_markdown_ext(text: &str, options: Options) -> String {
+        let mut buffer = String::new();
+        crate::markdown::push_markdown(&mut buffer, crate::parser::Parser::new_ext(text, options));
+        buffer
+    }
+
+    #[test]
+    fn push_markdown_round_trips_spec_examples() {
+        for entry in std::fs::read_dir("tests/fixtures/markdowns").unwrap() {
+            let path = entry.unwrap().path();
+            let text = std::fs::read_to_string(&path).unwrap();
+            assert_eq!(
+                convert(&convert_markdown(&text)),
+                convert(&text),
+                "{:?}",
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn push_markdown_round_trips_edge_cases() {
+        for text in [
+            "    ]",
+            "```\n]",
+            "> ```\n> a\n> b",
+            "```\\&amp;\n```\n",
+            "~~~ a`b\\`&lt;\n~~~\n",
+            "* - -\n",
+            "<b>x</b>\n",
+            "[a]: /u\n<b>\nx\n",
+            "- [a]: /u\n  <b>\n",
+        ] {
+            assert_eq!(
+                convert(&convert_markdown(text)),
+                convert(text),
+                "{:?}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn push_markdown_normalizes_blocks() {
+        assert_eq!(
+            convert_markdown(concat!(
+                "Title\n",
+                "=====\n",
+                "\n",
+                "* a *b*\n",
+                "* 1\\. c\n",
+                "+ d\n",
+                "\n",
+                ">     ```\n",
+                ">\n",
+                ">     code\n",
+            )),
+            concat!(
+                "# Title\n",
+                "\n",
+                "- a *b*\n",
+                "- 1\\. c\n",
+                "\n",
+                "* d\n",
+                "\n",
+                "> ````\n",
+                "> ```\n",
+                ">\n",
+                "> code\n",
+                "> ````\n",
+            )
+        );
+    }
+
+    #[test]
+    fn write_html_into_io_write() {
+        let text = include_str!("../tests/fixtures/extensions/markdowns/footnotes.md");
+        let options = Options::ENABLE_FOOTNOTES | Options::ENABLE_FOOTNOTE_SECTION;
+        let mut buffer = Vec::new();
+        crate::html::write_html_ext(
+            &mut buffer,
+            crate::parser::Parser::new_ext(text, options),
+            options,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            convert_ext(text, options)
+        );
+    }
+
+    #[test]
+    fn write_html_into_fmt_write() {
+        let text = "# Title\n\n- [link](/a b)\n";
+        let mut buffer = String::new();
+        crate::html::write_html_fmt(&mut buffer, crate::parser::Parser::new(text)).unwrap();
+        assert_eq!(buffer, convert(text));
+    }
+
+    #[test]
+    fn push_html_with_custom_renderer() {
+        use crate::html::{HtmlOutput, HtmlRenderer};
+        use std::io;
+
+        struct Renderer;
+
+        impl HtmlRenderer for Renderer {
+            fn begin_fenced_code_block(
+                &mut self,
+                output: &mut HtmlOutput,
+                _info: &str,
+            ) -> io::Result<()> {
+                output.write_line_ending_if_needed()?;
+                output.write("<figure><pre><code>")
+            }
+
+            fn end_fenced_code_block(
+                &mut self,
+                output: &mut HtmlOutput,
+                _info: &str,
+            ) -> io::Result<()> {
+                output.write("</code></pre></figure>\n")
+            }
+
+            fn thematic_break(&mut self, output: &mut HtmlOutput) -> io::Result<()> {
+                output.write_line_ending_if_needed()?;
+                output.write("<hr>\n")
+            }
+        }
+
+        let mut buffer = String::new();
+        crate::html::push_html_with(
+            &mut buffer,
+            crate::parser::Parser::new("```rust\nfoo\n```\n***\n*bar*\n"),
+            Options::empty(),
+            &mut Renderer,
+        );
+        assert_eq!(
+            buffer,
+            "<figure><pre><code>foo\n</code></pre></figure>\n<hr>\n<p><em>bar</em></p>\n"
+        );
+    }
+
+    #[test]
+    fn write_html_buffers_fragments() {
+        struct CountingWriter(Vec<u8>, usize);
+
+        impl std::io::Write for CountingWriter {
+            fn write(&mut self, buffer: &[u8]) -> std::io::Result<usize> {
+                self.1 += 1;
+                self.0.write(buffer)
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let text = "# Title\n\n- [link](/a b)\n";
+        let mut writer = CountingWriter(Vec::new(), 0);
+        crate::html::write_html(&mut writer, crate::parser::Parser::new(text)).unwrap();
+        assert_eq!(String::from_utf8(writer.0).unwrap(), convert(text));
+        assert_eq!(writer.1, 1);
+    }
+
+    #[test]
+    fn write_html_propagates_io_error() {
+        struct FailingWriter;
+
+        impl std::io::Write for FailingWriter {
+            fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::new(
+                    std::io::ErrorKind::Other,
+                    "broken pipe",
+                ))
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let result = crate::html::write_html(FailingWriter, crate::parser::Parser::new("text"));
+        assert_eq!(result.unwrap_err().to_string(), "broken pipe");
+    }
+
+    #[test]
+    fn into_offset_iter_yields_source_ranges() {
+        use crate::types::{Event, Tag};
+        use std::borrow::Cow;
+
+        let text = "# Title\n\n- *a* `b`\n---\n";
+        let events: Vec<_> = crate::parser::Parser::new(text)
+            .into_offset_iter()
+            .collect();
+        let heading = Tag::Heading {
+            level: crate::types::HeadingLevel::H1,
+            id: None,
+            classes: Vec::new(),
+        };
+        assert_eq!(
+            events,
+            vec![
+                (Event::Begin(heading.clone()), 0..8),
+                (Event::Text(Cow::Borrowed("Title")), 2..7),
+                (Event::End(heading), 0..8),
+                (Event::Begin(Tag::List(None)), 9..19),
+                (Event::Begin(Tag::Item), 9..19),
+                (Event::Begin(Tag::Emphasis), 11..14),
+                (Event::Text(Cow::Borrowed("a")), 12..13),
+                (Event::End(Tag::Emphasis), 11..14),
+                (Event::Text(Cow::Borrowed(" ")), 14..15),
+                (Event::Code(Cow::Borrowed("b")), 15..18),
+                (Event::End(Tag::Item), 9..19),
+                (Event::End(Tag::List(None)), 9..19),
+                (Event::ThematicBreak, 19..23),
+            ]
+        );
+    }
+
+    #[test]
+    fn push_html_offsets_with_sourcepos() {
+        let text = "# Title\n\n> - a\n>   b\n\n***\n";
+        let mut buffer = String::new();
+        crate::html::push_html_offsets(
+            &mut buffer,
+            text,
+            crate::parser::Parser::new(text).into_offset_iter(),
+            Options::ENABLE_SOURCEPOS,
+        );
+        assert_eq!(
+            buffer,
+            concat!(
+                "<h1 data-sourcepos=\"1:1-1:7\">Title</h1>\n",
+                "<blockquote data-sourcepos=\"3:1-4:5\">\n",
+                "<ul data-sourcepos=\"3:3-4:5\">\n",
+                "<li data-sourcepos=\"3:3-4:5\">a\nb</li>\n",
+                "</ul>\n",
+                "</blockquote>\n",
+                "<hr data-sourcepos=\"6:1-6:3\" />\n",
+            )
+        );
+    }
+
+    #[test]
+    fn line_index_position() {
+        let line_index = crate::line_index::LineIndex::new("ab\r\nc\rd\n\ne");
+        assert_eq!(line_index.position(0), (1, 1));
+        assert_eq!(line_index.position(3), (1, 4));
+        assert_eq!(line_index.position(4), (2, 1));
+        assert_eq!(line_index.position(6), (3, 1));
+        assert_eq!(line_index.position(9), (5, 1));
+    }
+
+    #[test]
+    fn push_html_offsets_without_sourcepos() {
+        let text = "- a\n\n  b\n";
+        let mut buffer = String::new();
+        crate::html::push_html_offsets(
+            &mut buffer,
+            text,
+            crate::parser::Parser::new(text).into_offset_iter(),
+            Options::empty(),
+        );
+        assert_eq!(buffer, convert(text));
+    }
+
+    #[test]
+    fn ast_round_trips_events() {
+        for directory in ["tests/fixtures/markdowns", "tests/fixtures/gfm/markdowns"] {
+            for entry in std::fs::read_dir(directory).unwrap() {
+                let path = entry.unwrap().path();
+                let text = std::fs::read_to_string(&path).unwrap();
+                let events: Vec<_> =
+                    crate::parser::Parser::new_ext(&text, Options::all()).collect();
+                let node = crate::ast::Node::from_events(events.clone());
+                assert_eq!(node.events().collect::<Vec<_>>(), events, "{:?}", path);
+            }
+        }
+    }
+
+    #[test]
+    fn ast_ignores_unmatched_events() {
+        use crate::ast::{Node, NodeKind};
+        use crate::types::{Event, Tag};
+        use std::borrow::Cow;
+
+        let node = Node::from_events(vec![
+            Event::End(Tag::Paragraph),
+            Event::Begin(Tag::Emphasis),
+            Event::Text(Cow::Borrowed("a")),
+        ]);
+        assert_eq!(
+            node,
+            Node::with_children(
+                NodeKind::Document,
+                vec![Node::with_children(
+                    NodeKind::Emphasis,
+                    vec![Node::new(NodeKind::Text("a".to_string()))],
+                )],
+            )
+        );
+    }
+
+    #[test]
+    fn ast_edits_children() {
+        use crate::ast::{Node, NodeKind};
+
+        let mut document = Node::from_events(crate::parser::Parser::new("- a\n- b\n\n***\n"));
+        let list = &mut document.children_mut()[0];
+        let item = list.remove_child(0);
+        list.append_child(item);
+        list.insert_child(
+            0,
+            Node::with_children(
+                NodeKind::Item,
+                vec![Node::new(NodeKind::Text("c".to_string()))],
+            ),
+        );
+        let thematic_break = document.replace_child(
+            1,
+            Node::with_children(
+                NodeKind::CodeBlock(Some("rust".to_string())),
+                vec![Node::new(NodeKind::Text("d\n".to_string()))],
+            ),
+        );
+        assert_eq!(thematic_break, Node::new(NodeKind::ThematicBreak));
+
+        let mut buffer = String::new();
+        crate::html::push_html(&mut buffer, document.events());
+        assert_eq!(
+            buffer,
+            concat!(
+                "<ul>\n",
+                "<li>c</li>\n",
+                "<li>b</li>\n",
+                "<li>a</li>\n",
+                "</ul>\n",
+                "<pre><code class=\"language-rust\">d\n",
+                "</code></pre>\n",
+            )
+        );
+    }
+
+    #[test]
+    fn visitor_enters_and_leaves_in_order() {
+        use crate::ast::{Node, NodeKind};
+        use crate::visit::Visitor;
+
+        #[derive(Default)]
+        struct Logger(Vec<String>);
+
+        impl Visitor for Logger {
+            fn enter(&mut self, node: &Node) {
+                self.0.push(format!("enter {:?}", node.kind));
+            }
+
+            fn leave(&mut self, node: &Node) {
+                self.0.push(format!("leave {:?}", node.kind));
+            }
+
+            // Skip children of links.
+            fn visit_link(&mut self, _node: &Node) {}
+        }
+
+        let document = Node::from_events(crate::parser::Parser::new("*a* [b](c)\n"));
+        let mut logger = Logger::default();
+        logger.visit_node(&document);
+        assert_eq!(
+            logger.0,
+            vec![
+                "enter Document",
+                "enter Paragraph",
+                "enter Emphasis",
+                "enter Text(\"a\")",
+                "leave Text(\"a\")",
+                "leave Emphasis",
+                "enter Text(\" \")",
+                "leave Text(\" \")",
+                "enter Link { dest: \"c\", title: \"\" }",
+                "leave Link { dest: \"c\", title: \"\" }",
+                "leave Paragraph",
+                "leave Document",
+            ]
+        );
+        assert!(matches!(document.kind, NodeKind::Document));
+    }
+
+    #[test]
+    fn visitor_mut_rewrites_relative_links() {
+        use crate::ast::{Node, NodeKind};
+        use crate::visit::VisitorMut;
+
+        struct LinkRewriter;
+
+        impl VisitorMut for LinkRewriter {
+            fn enter(&mut self, node: &mut Node) {
+                if let NodeKind::Link { dest, .. } | NodeKind::Image { dest, .. } = &mut node.kind {
+                    if !dest.contains(':') && !dest.starts_with('/') {
+                        *dest = format!("/docs/{}", dest);
+                    }
+                }
+            }
+        }
+
+        let mut document = Node::from_events(crate::parser::Parser::new(
+            "> [a](a.md) [b](https://b) ![c](c.png)\n",
+        ));
+        LinkRewriter.visit_node(&mut document);
+        let mut buffer = String::new();
+        crate::html::push_html(&mut buffer, document.events());
+        assert_eq!(
+            buffer,
+            concat!(
+                "<blockquote>\n",
+                "<p><a href=\"/docs/a.md\">a</a> <a href=\"https://b\">b</a> ",
+                "<img src=\"/docs/c.png\" alt=\"c\" /></p>\n",
+                "</blockquote>\n",
+            )
+        );
+    }
+
+    #[test]
+    fn fold_dispatches_by_node_kind() {
+        use crate::ast::{Node, NodeKind};
+        use crate::visit::Fold;
+
+        #[derive(Default)]
+        struct Folder(Vec<String>);
+
+        impl Fold for Folder {
+            fn fold_leave(&mut self, node: Node) -> Vec<Node> {
+                self.0.push(format!("leave {:?}", node.kind));
+                vec![node]
+            }
+
+            // Keep code spans in block quotes as they are, by not folding the children.
+            fn fold_block_quote(&mut self, node: Node) -> Vec<Node> {
+                vec![node]
+            }
+
+            fn fold_code(&mut self, node: Node) -> Vec<Node> {
+                match node.kind {
+                    NodeKind::Code(value) => vec![Node::new(NodeKind::Text(value))],
+                    _ => unreachable!(),
+                }
+            }
+
+            fn fold_thematic_break(&mut self, _node: Node) -> Vec<Node> {
+                Vec::new()
+            }
+        }
+
+        let mut folder = Folder::default();
+        let document = folder.fold_node(Node::from_events(crate::parser::Parser::new(
+            "`a`\n\n---\n\n> `b`\n",
+        )));
+        let mut buffer = String::new();
+        crate::html::push_html(&mut buffer, document.iter().flat_map(Node::events));
+        assert_eq!(
+            buffer,
+            "<p>a</p>\n<blockquote>\n<p><code>b</code></p>\n</blockquote>\n"
+        );
+        assert_eq!(
+            folder.0,
+            vec![
+                "leave Text(\"a\")",
+                "leave Paragraph",
+                "leave BlockQuote",
+                "leave Document",
+            ]
+        );
+    }
+
+    #[test]
+    fn tree_traverses_with_node_refs() {
+        use crate::tree::{NodeRef, Tree, TreeCursor};
+
+        let mut tree = Tree::new();
+        let mut cursor = TreeCursor::new();
+        cursor.open(&mut tree, "a");
+        cursor.append(&mut tree, "b");
+        cursor.open(&mut tree, "c");
+        cursor.append(&mut tree, "d");
+        cursor.close();
+        cursor.close();
+        cursor.append(&mut tree, "e");
+
+        fn items<'t>(nodes: impl Iterator<Item = NodeRef<'t, &'static str>>) -> Vec<&'static str> {
+            nodes.map(|node| *node.item()).collect()
+        }
+
+        assert_eq!(items(tree.descendants()), ["a", "b", "c", "d", "e"]);
+        assert_eq!(items(tree.roots()), ["a", "e"]);
+        let a = tree.first().unwrap();
+        assert_eq!(items(a.children()), ["b", "c"]);
+        assert_eq!(items(a.descendants()), ["b", "c", "d"]);
+        let d = a.descendants().last().unwrap();
+        assert_eq!(items(d.ancestors()), ["c", "a"]);
+        assert!(d.next_sibling().is_none() && d.first_child().is_none());
+        assert_eq!(*d.parent().unwrap().previous_sibling().unwrap().item(), "b");
+        assert!(tree.get(5).is_none() && tree.item(5).is_none());
+
+        // Cursors move independently, and fail without panics at the ends.
+        let mut first = TreeCursor::new();
+        let mut second = TreeCursor::new();
+        first.go_to_first(&tree);
+        second.go_to_first(&tree);
+        assert!(first.go_to_child(&tree));
+        assert!(first.go_to_next_sibling(&tree));
+        assert!(second.go_to_next_sibling(&tree));
+        assert_eq!(
+            first.current().and_then(|index| tree.item(index).copied()),
+            Some("c")
+        );
+        assert_eq!(
+            second.current().and_then(|index| tree.item(index).copied()),
+            Some("e")
+        );
+        assert!(!second.go_to_next_sibling(&tree));
+        assert!(!second.go_to_child(&tree));
+        assert!(!second.go_to_parent());
+        assert_eq!(
+            second.current().and_then(|index| tree.item(index).copied()),
+            Some("e")
+        );
+        assert!(first.go_to_child(&tree));
+        assert!(!first.go_to_next_sibling(&tree));
+        assert_eq!(
+            first.current().and_then(|index| tree.item(index).copied()),
+            Some("d")
+        );
+        assert!(first.go_to_parent());
+        assert!(!first.go_to_child(&Tree::<&str>::new()));
+
+        let c = first.current().unwrap();
+        first.remove_current(&mut tree);
+        assert_eq!(
+            first.current().and_then(|index| tree.item(index).copied()),
+            Some("b")
+        );
+        assert_eq!(items(tree.descendants()), ["a", "b", "e"]);
+        tree.remove(c);
+        assert_eq!(items(tree.descendants()), ["a", "b", "e"]);
+    }
 }
@@ -0,0 +1,25 @@
+/// Index of the beginnings of lines in text, to convert byte offsets into line and column numbers.
+#[derive(Clone, Debug)]
+pub struct LineIndex {
+    line_begins: Vec<usize>,
+}
+
+impl LineIndex {
+    pub fn new(text: &str) -> Self {
+        let bytes = text.as_bytes();
+        let mut line_begins = vec![0];
+        for (index, &byte) in bytes.iter().enumerate() {
+            // `\r\n` is counted as one line ending at its `\n`.
+            if byte == b'\n' || byte == b'\r' && bytes.get(index + 1) != Some(&b'\n') {
+                line_begins.push(index + 1);
+            }
+        }
+        Self { line_begins }
+    }
+
+    /// Return 1-based line and column numbers of given byte offset, where columns are counted in bytes as cmark does.
+    pub fn position(&self, offset: usize) -> (usize, usize) {
+        let line = self.line_begins.partition_point(|&begin| begin <= offset);
+        (line, offset - self.line_begins[line - 1] + 1)
+    }
+}
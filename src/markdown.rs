@@ -0,0 +1,627 @@
+use crate::parser::Parser;
+use crate::types::Event::*;
+use crate::types::Tag::*;
+use crate::types::{Alignment, Event, Tag};
+use std::iter::Iterator;
+
+/// Push normalized CommonMark text of given events, which is parsed into the same events again.
+///
+/// Events are collected before writing, since some blocks need to look ahead up to their ends,
+/// which can be as far as the end of document (e.g. whether a list is tight, or a heading has line breaks).
+pub fn push_markdown<'a, I>(writer: &mut String, iterator: I)
+where
+    I: Iterator<Item = Event<'a>>,
+{
+    MarkdownWriter::new(writer, iterator.collect()).run();
+}
+
+/// Container block whose lines are prefixed (e.g. `> ` for block quote).
+struct Container {
+    prefix: String,
+    /// Whether the container is an item of tight list, whose blocks are not separated by blank lines.
+    tight: bool,
+    /// Length of the written text when the container begins.
+    begin: usize,
+}
+
+struct List {
+    marker: u8,
+    number: Option<u64>,
+    tight: bool,
+}
+
+/// How to separate the next block from the previous one.
+#[derive(Clone, Copy, PartialEq)]
+enum Separator {
+    BlankLine,
+    LineEnding,
+    None,
+}
+
+struct MarkdownWriter<'a, 'w> {
+    events: Vec<Event<'a>>,
+    index: usize,
+    writer: &'w mut String,
+    containers: Vec<Container>,
+    lists: Vec<List>,
+    separator: Separator,
+    /// Marker of the list just closed, so that the next adjacent list uses another marker not to be merged with it.
+    closed_list_marker: Option<u8>,
+    /// Whether nothing (including container prefixes) is written on the current line.
+    line_start: bool,
+    /// Length of inline content on the current line if it consists only of digits,
+    /// where some characters need escaping at the beginning of line (e.g. `#`, or `.` after digits).
+    line_digits: Option<usize>,
+    /// Delimiters of the currently open emphasis and strong emphasis.
+    emphasis_delimiters: Vec<&'static str>,
+    heading_open: bool,
+    /// Whether the open heading has multiple lines, which can only be written as setext heading.
+    setext_heading: bool,
+    table_alignments: Vec<Alignment>,
+    table_cell_open: bool,
+}
+
+impl<'a, 'w> MarkdownWriter<'a, 'w> {
+    fn new(writer: &'w mut String, events: Vec<Event<'a>>) -> Self {
+        Self {
+            events,
+            index: 0,
+            writer,
+            containers: Vec::new(),
+            lists: Vec::new(),
+            separator: Separator::None,
+            closed_list_marker: None,
+            line_start: true,
+            line_digits: Some(0),
+            emphasis_delimiters: Vec::new(),
+            heading_open: false,
+            setext_heading: false,
+            table_alignments: Vec::new(),
+            table_cell_open: false,
+        }
+    }
+
+    fn run(mut self) {
+        while self.index < self.events.len() {
+            let event = self.events[self.index].clone();
+            self.index += 1;
+            if !matches!(event, Begin(Tag::List(_)) | End(Tag::List(_))) {
+                self.closed_list_marker = None;
+            }
+            match event {
+                Begin(tag) => self.begin_tag(tag),
+                End(tag) => self.end_tag(tag),
+                Code(value) => {
+                    self.write_code(&value);
+                }
+                FootnoteReference(label) => {
+                    self.write("[^");
+                    self.write(label);
+                    self.write("]");
+                }
+                HardBreak => {
+                    self.write("\\");
+                    self.write_line_ending();
+                }
+                Html(value) => {
+                    // HTML block has no tags, so it begins with the first line not following another line.
+                    if !matches!(self.events.get(self.index.wrapping_sub(2)), Some(Html(_))) {
+                        self.begin_block();
+                    }
+                    self.write_raw(value);
+                    if !matches!(self.events.get(self.index), Some(Html(_))) {
+                        self.end_block();
+                    }
+                }
+                InlineHtml(value) => {
+                    // Inline HTML at the beginning of paragraph may start HTML block instead (e.g. `<b>` on its own line),
+                    // which is avoided by an unused link reference definition making the line a continuation.
+                    if matches!(
+                        self.events.get(self.index.wrapping_sub(2)),
+                        Some(Begin(Tag::Paragraph | Tag::Item))
+                    ) && self.starts_html_block(value)
+                    {
+                        self.write("[_]: <>");
+                        self.write_line_ending();
+                    }
+                    self.write(value);
+                }
+                SoftBreak => {
+                    if (self.heading_open && !self.setext_heading) || self.table_cell_open {
+                        self.write(" ");
+                    } else {
+                        self.write_line_ending();
+                    }
+                }
+                TaskListMarker(checked) => {
+                    self.write(if checked { "[x] " } else { "[ ] " });
+                }
+                Text(value) => {
+                    self.write_escaped_text(&value);
+                }
+                ThematicBreak => {
+                    self.begin_block();
+                    self.write("___");
+                    self.end_block();
+                }
+            }
+        }
+        self.write_line_ending_if_needed();
+    }
+
+    fn begin_tag(&mut self, tag: Tag<'a>) {
+        match tag {
+            BlockQuote => {
+                self.begin_block();
+                let begin = self.writer.len();
+                // Block quote may begin on the same line as the marker of list item or footnote definition.
+                if !self.line_start {
+                    self.write("> ");
+                    self.line_digits = Some(0);
+                }
+                self.containers.push(Container {
+                    prefix: "> ".to_string(),
+                    tight: false,
+                    begin,
+                });
+            }
+            Emphasis => {
+                self.begin_emphasis("*", "_");
+            }
+            FencedCodeBlock(info) => {
+                self.write_code_block(&info);
+            }
+            IndentedCodeBlock => {
+                self.write_code_block("");
+            }
+            FootnoteDefinition(label) => {
+                self.begin_block();
+                self.write("[^");
+                self.write(label);
+                self.write("]: ");
+                self.line_digits = Some(0);
+                self.containers.push(Container {
+                    prefix: "    ".to_string(),
+                    tight: false,
+                    begin: self.writer.len(),
+                });
+            }
+            Heading { level, .. } => {
+                self.begin_block();
+                self.heading_open = true;
+                self.setext_heading = level as usize <= 2 && self.has_line_break();
+                if !self.setext_heading {
+                    self.write(&"#".repeat(level as usize));
+                    self.write(" ");
+                }
+            }
+            Image { .. } => {
+                self.write("![");
+            }
+            Item => {
+                self.begin_block();
+                let list = self.lists.last_mut().unwrap();
+                let marker = match list.number {
+                    Some(number) => {
+                        list.number = Some(number + 1);
+                        format!("{}{} ", number, list.marker as char)
+                    }
+                    None => format!("{} ", list.marker as char),
+                };
+                let tight = list.tight;
+                self.write(&marker);
+                self.line_digits = Some(0);
+                self.containers.push(Container {
+                    prefix: " ".repeat(marker.len()),
+                    tight,
+                    begin: self.writer.len(),
+                });
+            }
+            Link { .. } => {
+                self.write("[");
+            }
+            List(start) => {
+                let closed_list_marker = self.closed_list_marker.take();
+                self.begin_block();
+                // Bullets alternate by nesting level, not to make nested empty items (e.g. `- - -`) a thematic break.
+                let (bullet, alternative) = if self.lists.len() % 2 == 0 {
+                    (b'-', b'*')
+                } else {
+                    (b'*', b'-')
+                };
+                let marker = match (start, closed_list_marker) {
+                    (Some(_), Some(b'.')) => b')',
+                    (Some(_), _) => b'.',
+                    (None, Some(marker)) if marker == bullet => alternative,
+                    (None, _) => bullet,
+                };
+                self.lists.push(List {
+                    marker,
+                    number: start,
+                    tight: self.is_tight_list(),
+                });
+            }
+            Paragraph => {
+                self.begin_block();
+            }
+            Strikethrough => {
+                self.write("~~");
+            }
+            Strong => {
+                self.begin_emphasis("**", "__");
+            }
+            Table(alignments) => {
+                self.begin_block();
+                self.table_alignments = alignments;
+            }
+            TableCell => {
+                self.write(" ");
+                self.table_cell_open = true;
+            }
+            TableHead | TableRow => {
+                self.write("|");
+            }
+        }
+    }
+
+    fn end_tag(&mut self, tag: Tag<'a>) {
+        match tag {
+            BlockQuote | FootnoteDefinition(_) => {
+                self.write_line_ending_if_needed();
+                let container = self.containers.pop().unwrap();
+                // Empty block quote still needs its marker.
+                if self.writer.len() == container.begin {
+                    self.write(container.prefix.trim_end());
+                }
+                self.end_block();
+            }
+            Emphasis | Strong => {
+                let delimiter = self.emphasis_delimiters.pop().unwrap();
+                self.write(delimiter);
+            }
+            FencedCodeBlock(_) | IndentedCodeBlock => {
+                // Already written by its beginning event, which consumes events until the end.
+            }
+            Heading { level, id, classes } => {
+                if id.is_some() || !classes.is_empty() {
+                    self.write(" {");
+                    let attributes: Vec<_> = id
+                        .map(|id| format!("#{}", id))
+                        .into_iter()
+                        .chain(classes.iter().map(|class| format!(".{}", class)))
+                        .collect();
+                    self.write(&attributes.join(" "));
+                    self.write("}");
+                }
+                if self.setext_heading {
+                    self.write_line_ending();
+                    self.write(if level as usize == 1 { "===" } else { "---" });
+                }
+                self.heading_open = false;
+                self.end_block();
+            }
+            Item => {
+                self.write_line_ending_if_needed();
+                self.containers.pop();
+                // Items of loose list are separated by blank lines.
+                self.separator = if self.lists.last().unwrap().tight {
+                    Separator::LineEnding
+                } else {
+                    Separator::BlankLine
+                };
+            }
+            Image { dest, title } | Link { dest, title } => {
+                self.write("](");
+                self.write_link_destination(&dest);
+                if !title.is_empty() {
+                    self.write(" \"");
+                    self.write(&escape(&title, |c| matches!(c, '"' | '\\')));
+                    self.write("\"");
+                }
+                self.write(")");
+            }
+            List(_) => {
+                self.closed_list_marker = self.lists.pop().map(|list| list.marker);
+                self.separator = self.block_separator();
+            }
+            Paragraph => {
+                self.end_block();
+            }
+            Strikethrough => {
+                self.write("~~");
+            }
+            Table(_) => {
+                self.end_block();
+            }
+            TableCell => {
+                self.write(" |");
+                self.table_cell_open = false;
+            }
+            TableHead => {
+                self.write_line_ending();
+                self.write("|");
+                for alignment in self.table_alignments.clone() {
+                    self.write(match alignment {
+                        Alignment::Center => " :-: |",
+                        Alignment::Left => " :-- |",
+                        Alignment::None => " --- |",
+                        Alignment::Right => " --: |",
+                    });
+                }
+                self.write_line_ending();
+            }
+            TableRow => {
+                self.write_line_ending();
+            }
+        }
+    }
+
+    /// Separate new block from the previous one.
+    fn begin_block(&mut self) {
+        // Inline content directly in tight list item has no paragraph tags,
+        // while the first block in list item or footnote definition follows its marker on the same line.
+        if self.line_digits != Some(0) {
+            self.write_line_ending();
+        }
+        if self.separator == Separator::BlankLine {
+            self.write_line_ending();
+        }
+        self.separator = Separator::None;
+    }
+
+    fn end_block(&mut self) {
+        self.write_line_ending_if_needed();
+        self.separator = self.block_separator();
+    }
+
+    /// Return separator between blocks in the current container.
+    fn block_separator(&self) -> Separator {
+        match self.containers.last() {
+            Some(container) if container.tight => Separator::LineEnding,
+            _ => Separator::BlankLine,
+        }
+    }
+
+    /// Write delimiter of emphasis or strong emphasis, or the alternative one if it follows another `*`,
+    /// since adjacent delimiters are merged into one run (e.g. `***`).
+    /// The alternative one is not used in a word, where `_` cannot make emphasis.
+    fn begin_emphasis(&mut self, delimiter: &'static str, alternative: &'static str) {
+        let previous = self.writer.trim_end_matches('*');
+        let delimiter = if previous.len() < self.writer.len()
+            && !previous.ends_with(|c: char| c.is_alphanumeric())
+        {
+            alternative
+        } else {
+            delimiter
+        };
+        self.emphasis_delimiters.push(delimiter);
+        self.write(delimiter);
+    }
+
+    /// Check if the heading just begun has any line break.
+    fn has_line_break(&self) -> bool {
+        self.events[self.index..]
+            .iter()
+            .take_while(|event| !matches!(event, End(Tag::Heading { .. })))
+            .any(|event| matches!(event, HardBreak | SoftBreak))
+    }
+
+    /// Check if given inline HTML just taken would start HTML block at the beginning of line.
+    fn starts_html_block(&self, value: &str) -> bool {
+        // Whether the line has nothing else matters for some kinds of HTML blocks.
+        let line = match self.events.get(self.index) {
+            None | Some(SoftBreak | End(_)) => value.to_string(),
+            Some(_) => format!("{}_", value),
+        };
+        matches!(Parser::new(&line).next(), Some(Html(_)))
+    }
+
+    /// Check if the list just begun is tight, i.e. none of its items has paragraph tags.
+    fn is_tight_list(&self) -> bool {
+        let mut depth = 0;
+        for event in &self.events[self.index..] {
+            match event {
+                Begin(Tag::Paragraph) if depth == 1 => return false,
+                Begin(_) => depth += 1,
+                End(_) if depth == 0 => break,
+                End(_) => depth -= 1,
+                _ => {}
+            }
+        }
+        true
+    }
+
+    /// Write fenced code block with the content of the following text events,
+    /// where the fence is longer than any run of the same characters in the content.
+    fn write_code_block(&mut self, info: &str) {
+        let mut content = String::new();
+        while let Some(event) = self.events.get(self.index) {
+            self.index += 1;
+            match event {
+                Text(value) => content.push_str(value),
+                _ => break,
+            }
+        }
+        let fence_char = if info.contains('`') { '~' } else { '`' };
+        let fence_length = content
+            .split(|c| c != fence_char)
+            .map(str::len)
+            .max()
+            .unwrap_or(0)
+            .max(2)
+            + 1;
+        let fence = fence_char.to_string().repeat(fence_length);
+        self.begin_block();
+        self.write(&fence);
+        // Info string is unescaped when parsed, and backticks can be there only with escapes after tildes.
+        let mut escaped = String::new();
+        for c in info.chars() {
+            if matches!(c, '\\' | '&' | '`') {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+        }
+        self.write(&escaped);
+        self.write_line_ending();
+        self.write_raw(&content);
+        // Closing fence needs its own line.
+        if !content.is_empty() && !content.ends_with('\n') {
+            self.write_line_ending();
+        }
+        self.write(&fence);
+        self.end_block();
+    }
+
+    /// Write code span with backticks, which are longer or shorter than any run of backticks in the content.
+    fn write_code(&mut self, value: &str) {
+        let mut length = 1;
+        while value
+            .split(|c| c != '`')
+            .any(|backticks| backticks.len() == length)
+        {
+            length += 1;
+        }
+        let backticks = "`".repeat(length);
+        // One space is stripped from both sides if the content begins and ends with space.
+        let padding = value.starts_with('`')
+            || value.ends_with('`')
+            || (value.starts_with(' ') && value.ends_with(' ') && !value.trim().is_empty());
+        self.write(&backticks);
+        if padding {
+            self.write(" ");
+        }
+        // `|` needs escaping even in code span in table cell.
+        if self.table_cell_open {
+            self.write(&value.replace('|', "\\|"));
+        } else {
+            self.write(value);
+        }
+        if padding {
+            self.write(" ");
+        }
+        self.write(&backticks);
+    }
+
+    /// Write link destination, which is enclosed in `<` and `>` if it contains spaces or parentheses.
+    fn write_link_destination(&mut self, value: &str) {
+        if value.is_empty()
+            || value.contains(|c: char| c == ' ' || c == '(' || c == ')' || c.is_control())
+        {
+            self.write("<");
+            self.write(&escape(value, |c| matches!(c, '<' | '>' | '\\')));
+            self.write(">");
+        } else {
+            self.write(&escape(value, |c| matches!(c, '<' | '>' | '\\')));
+        }
+    }
+
+    /// Write text with escaping characters that would be interpreted as markup.
+    fn write_escaped_text(&mut self, value: &str) {
+        let mut escaped = String::new();
+        let line_digits = self.line_digits;
+        let line_start = line_digits == Some(0);
+        for (index, c) in value.char_indices() {
+            let needs_escape = match c {
+                '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '~' => true,
+                // `!` just before link would make it an image.
+                '!' => index + 1 == value.len(),
+                '#' => self.heading_open || (line_start && index == 0),
+                '|' => self.table_cell_open,
+                '&' => is_entity_like(&value[index..]),
+                '>' | '-' | '+' | '=' => line_start && index == 0,
+                // Escape the delimiter of ordered list marker (e.g. `1.`).
+                '.' | ')' => line_digits.map_or(false, |length| {
+                    length + index > 0 && value[..index].bytes().all(|byte| byte.is_ascii_digit())
+                }),
+                // Line endings and leading whitespaces are written as numeric character references not to be stripped.
+                '\n' | '\r' => {
+                    escaped.push_str(&format!("&#{};", c as u32));
+                    continue;
+                }
+                ' ' | '\t' if line_start && index == 0 => {
+                    escaped.push_str(&format!("&#{};", c as u32));
+                    continue;
+                }
+                _ => false,
+            };
+            if needs_escape {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+        }
+        self.write(&escaped);
+        self.line_digits = line_digits
+            .filter(|_| value.bytes().all(|byte| byte.is_ascii_digit()))
+            .map(|length| length + value.len());
+    }
+
+    /// Write given raw text line by line, with prefixes of the current containers.
+    fn write_raw(&mut self, value: &str) {
+        for line in value.split_inclusive('\n') {
+            match line.strip_suffix('\n') {
+                Some(line) => {
+                    self.write(line);
+                    self.write_line_ending();
+                }
+                None => self.write(line),
+            }
+        }
+    }
+
+    /// Write given text in a line, with prefixes of the current containers if at the beginning of the line.
+    fn write(&mut self, value: &str) {
+        if value.is_empty() {
+            return;
+        }
+        if self.line_start {
+            for container in &self.containers {
+                self.writer.push_str(&container.prefix);
+            }
+            self.line_start = false;
+        }
+        self.writer.push_str(value);
+        self.line_digits = None;
+    }
+
+    /// Write line ending, where blank line is written with prefixes of the current containers without trailing spaces.
+    fn write_line_ending(&mut self) {
+        if self.line_start {
+            let prefix: String = self
+                .containers
+                .iter()
+                .map(|container| container.prefix.as_str())
+                .collect();
+            self.writer.push_str(prefix.trim_end());
+        }
+        self.writer.push('\n');
+        self.line_start = true;
+        self.line_digits = Some(0);
+    }
+
+    fn write_line_ending_if_needed(&mut self) {
+        if !self.line_start {
+            self.write_line_ending();
+        }
+    }
+}
+
+/// Return given text with `\` before characters matching given condition.
+fn escape(value: &str, condition: impl Fn(char) -> bool) -> String {
+    let mut escaped = String::new();
+    for c in value.chars() {
+        if condition(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Check if given text (beginning with `&`) begins with something like entity or numeric character reference.
+fn is_entity_like(value: &str) -> bool {
+    let length = value[1..]
+        .bytes()
+        .take_while(|&byte| byte.is_ascii_alphanumeric() || byte == b'#')
+        .count();
+    length > 0 && value[1 + length..].starts_with(';')
+}
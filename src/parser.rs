@@ -1,18 +1,133 @@
-use crate::tree::Tree;
-use crate::types::{Block, BlockKind, Event, Tag};
+use crate::block_level_parser;
+use crate::inline_level_parser;
+use crate::tree::{Tree, TreeCursor};
+use crate::types::{Alignment, Block, BlockKind, Event, LinkReferenceDefinition, Options, Tag};
+use std::borrow::Cow;
+use std::collections::{HashMap, VecDeque};
 use std::iter::Iterator;
+use std::ops::Range;
 
 #[derive(Debug)]
 pub struct Parser<'a> {
+    cursor: TreeCursor,
+    definitions: HashMap<String, LinkReferenceDefinition<'a>>,
+    /// Whether all children of the current node are done, so that its end event comes next.
+    ending: bool,
+    events: VecDeque<(Event<'a>, Range<usize>)>,
+    footnote_definitions: HashMap<String, &'a str>,
+    options: Options,
     text: &'a str,
     tree: Tree<Block<'a>>,
 }
 
 impl<'a> Parser<'a> {
     pub fn new(text: &'a str) -> Self {
+        Self::new_ext(text, Options::empty())
+    }
+
+    /// Create parser with given extensions enabled.
+    pub fn new_ext(text: &'a str, options: Options) -> Self {
+        let (tree, definitions, footnote_definitions) =
+            block_level_parser::Parser::new(text, options).run();
+        let mut cursor = TreeCursor::new();
+        cursor.go_to_first(&tree);
         Self {
+            cursor,
+            definitions,
+            ending: false,
+            events: VecDeque::new(),
+            footnote_definitions,
+            options,
             text,
-            tree: text.into(),
+            tree,
+        }
+    }
+
+    /// Convert into iterator of events with their byte ranges in source text,
+    /// where the beginning and end events of an element have the range of the whole element.
+    pub fn into_offset_iter(self) -> OffsetIter<'a> {
+        OffsetIter { parser: self }
+    }
+
+    /// Parse inline-level elements from text nodes in the block of given index, and queue their events.
+    fn parse_inlines(&mut self, index: usize) {
+        let ranges = self
+            .tree
+            .get(index)
+            .unwrap()
+            .children()
+            .map(|child| child.item().begin..child.item().end + 1)
+            .collect();
+        self.events.extend(
+            inline_level_parser::Parser::new(self.text, ranges, self.options)
+                .run(&self.definitions, &self.footnote_definitions),
+        );
+    }
+
+    /// Return column alignments of the table of given index, which are kept by its head cells.
+    fn table_alignments(&self, index: usize) -> Vec<Alignment> {
+        let table = self.tree.get(index).unwrap();
+        let head = table.first_child().unwrap();
+        head.children()
+            .filter_map(|cell| match cell.item().kind {
+                BlockKind::TableCell(alignment) => Some(alignment),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Go to the first child of the current node, or to its end if it has no children.
+    fn go_to_child(&mut self) {
+        self.ending = !self.cursor.go_to_child(&self.tree);
+    }
+
+    /// Go to the next sibling of the current node, or to the end of its parent if it is the last one.
+    fn go_to_next_sibling(&mut self) {
+        if !self.cursor.go_to_next_sibling(&self.tree) {
+            if self.cursor.go_to_parent() {
+                self.ending = true;
+            } else {
+                // All top-level nodes are done.
+                self.cursor = TreeCursor::new();
+            }
+        }
+    }
+
+    /// Return task list marker event right before the paragraph of given index if any.
+    fn task_list_marker_before(&self, index: usize) -> Option<(Event<'a>, Range<usize>)> {
+        let paragraph = self.tree.get(index)?;
+        if !matches!(paragraph.item().kind, BlockKind::Paragraph) {
+            return None;
+        }
+        let marker = paragraph.previous_sibling()?.item();
+        match marker.kind {
+            BlockKind::TaskListMarker(checked) => {
+                Some((Event::TaskListMarker(checked), marker.begin..marker.end + 1))
+            }
+            _ => None,
+        }
+    }
+
+    /// Check if the last child of the block of given index has no line ending.
+    fn ends_without_line_ending(&self, index: usize) -> bool {
+        self.tree
+            .get(index)
+            .and_then(|node| node.children().last())
+            .map_or(false, |child| {
+                !matches!(
+                    self.text.as_bytes().get(child.item().end),
+                    Some(b'\n' | b'\r')
+                )
+            })
+    }
+
+    /// Check if the current node is a direct child of list item in tight list.
+    fn is_in_tight_list(&self) -> bool {
+        match self.cursor.ancestors() {
+            [.., list, _] => self.tree.item(*list).map_or(false, |block| {
+                matches!(block.kind, BlockKind::List { tight: true, .. })
+            }),
+            _ => false,
         }
     }
 }
@@ -22,60 +137,185 @@ impl<'a> Iterator for Parser<'a> {
     type Item = Event<'a>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        match self.tree.current {
-            Some(index) => {
-                let node = self.tree.nodes[index];
-                match node.item.kind {
-                    BlockKind::BlockQuote => {
-                        self.tree.go_to_child();
-                        Some(Event::Begin(Tag::BlockQuote))
-                    }
-                    BlockKind::FencedCodeBlock(info) => {
-                        self.tree.go_to_child();
-                        Some(Event::Begin(Tag::FencedCodeBlock(info)))
-                    }
-                    BlockKind::Heading(level) => {
-                        self.tree.go_to_child();
-                        Some(Event::Begin(Tag::Heading(level)))
-                    }
-                    BlockKind::Html => {
-                        self.tree.go_to_next_sibling();
-                        Some(Event::Html(&self.text[node.item.begin..node.item.end + 1]))
-                    }
-                    BlockKind::IndentedCodeBlock => {
-                        self.tree.go_to_child();
-                        Some(Event::Begin(Tag::IndentedCodeBlock))
-                    }
-                    BlockKind::Paragraph => {
-                        self.tree.go_to_child();
-                        Some(Event::Begin(Tag::Paragraph))
-                    }
-                    BlockKind::Text => {
-                        self.tree.go_to_next_sibling();
-                        Some(Event::Text(&self.text[node.item.begin..node.item.end + 1]))
+        self.next_event().map(|(event, _)| event)
+    }
+}
+
+/// Iterator of events with their byte ranges in source text, created by `Parser::into_offset_iter`.
+#[derive(Debug)]
+pub struct OffsetIter<'a> {
+    parser: Parser<'a>,
+}
+
+impl<'a> Iterator for OffsetIter<'a> {
+    type Item = (Event<'a>, Range<usize>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.parser.next_event()
+    }
+}
+
+impl<'a> Parser<'a> {
+    /// Return the next event with its byte range in source text.
+    fn next_event(&mut self) -> Option<(Event<'a>, Range<usize>)> {
+        if let Some(event) = self.events.pop_front() {
+            return Some(event);
+        }
+
+        let index = self.cursor.current()?;
+        let block = *self.tree.item(index)?;
+        if !std::mem::take(&mut self.ending) {
+            let range = block.begin..block.end + 1;
+            let event = match block.kind {
+                BlockKind::BlockQuote => {
+                    self.go_to_child();
+                    Event::Begin(Tag::BlockQuote)
+                }
+                BlockKind::FencedCodeBlock(info) => {
+                    self.go_to_child();
+                    Event::Begin(Tag::FencedCodeBlock(inline_level_parser::unescape(info)))
+                }
+                BlockKind::FootnoteDefinition(label) => {
+                    self.go_to_child();
+                    Event::Begin(Tag::FootnoteDefinition(label))
+                }
+                BlockKind::Heading(level, attributes) => {
+                    let (id, classes) = parse_heading_attributes(attributes.unwrap_or(""));
+                    let tag = Tag::Heading { level, id, classes };
+                    self.parse_inlines(index);
+                    self.events
+                        .push_back((Event::End(tag.clone()), range.clone()));
+                    self.go_to_next_sibling();
+                    Event::Begin(tag)
+                }
+                BlockKind::Html => {
+                    self.go_to_next_sibling();
+                    Event::Html(&self.text[range.clone()])
+                }
+                BlockKind::HtmlBlock => {
+                    self.go_to_child();
+                    return self.next_event();
+                }
+                BlockKind::IndentedCodeBlock => {
+                    self.go_to_child();
+                    Event::Begin(Tag::IndentedCodeBlock)
+                }
+                BlockKind::List { start, .. } => {
+                    self.go_to_child();
+                    Event::Begin(Tag::List(start))
+                }
+                BlockKind::ListItem(_) => {
+                    self.go_to_child();
+                    Event::Begin(Tag::Item)
+                }
+                BlockKind::Paragraph => {
+                    if let Some(marker) = self.task_list_marker_before(index) {
+                        self.events.push_back(marker);
                     }
-                    BlockKind::ThematicBreak => {
-                        self.tree.go_to_next_sibling();
-                        Some(Event::ThematicBreak)
+                    self.parse_inlines(index);
+                    let tight = self.is_in_tight_list();
+                    self.go_to_next_sibling();
+                    // Paragraphs in tight list are not wrapped by paragraph tags.
+                    if tight {
+                        return self.next_event();
                     }
+                    self.events
+                        .push_back((Event::End(Tag::Paragraph), range.clone()));
+                    Event::Begin(Tag::Paragraph)
                 }
-            }
-            None => {
-                self.tree.go_to_parent();
-                let index = self.tree.current?;
-                let event = match self.tree.nodes[index].item.kind {
-                    BlockKind::BlockQuote => Some(Event::End(Tag::BlockQuote)),
-                    BlockKind::FencedCodeBlock(info) => {
-                        Some(Event::End(Tag::FencedCodeBlock(info)))
+                BlockKind::Spaces(count) => {
+                    self.go_to_next_sibling();
+                    Event::Text(Cow::Borrowed(&"    "[..count]))
+                }
+                BlockKind::Table => {
+                    let alignments = self.table_alignments(index);
+                    self.go_to_child();
+                    Event::Begin(Tag::Table(alignments))
+                }
+                BlockKind::TableCell(_) => {
+                    self.parse_inlines(index);
+                    self.events
+                        .push_back((Event::End(Tag::TableCell), range.clone()));
+                    self.go_to_next_sibling();
+                    Event::Begin(Tag::TableCell)
+                }
+                BlockKind::TableHead => {
+                    self.go_to_child();
+                    Event::Begin(Tag::TableHead)
+                }
+                BlockKind::TableRow => {
+                    self.go_to_child();
+                    Event::Begin(Tag::TableRow)
+                }
+                BlockKind::TaskListMarker(checked) => {
+                    self.go_to_next_sibling();
+                    // Task list marker is put into the following paragraph if any.
+                    if self
+                        .cursor
+                        .current()
+                        .and_then(|next| self.task_list_marker_before(next))
+                        .is_some()
+                    {
+                        return self.next_event();
                     }
-                    BlockKind::Heading(level) => Some(Event::End(Tag::Heading(level))),
-                    BlockKind::IndentedCodeBlock => Some(Event::End(Tag::IndentedCodeBlock)),
-                    BlockKind::Paragraph => Some(Event::End(Tag::Paragraph)),
-                    _ => panic!("Unexpected node is found as a parent."),
-                };
-                self.tree.go_to_next_sibling();
-                event
+                    Event::TaskListMarker(checked)
+                }
+                BlockKind::Text => {
+                    self.go_to_next_sibling();
+                    Event::Text(Cow::Borrowed(&self.text[range.clone()]))
+                }
+                BlockKind::ThematicBreak => {
+                    self.go_to_next_sibling();
+                    Event::ThematicBreak
+                }
+            };
+            Some((event, range))
+        } else {
+            let event = match block.kind {
+                BlockKind::BlockQuote => Event::End(Tag::BlockQuote),
+                BlockKind::FencedCodeBlock(info) => {
+                    Event::End(Tag::FencedCodeBlock(inline_level_parser::unescape(info)))
+                }
+                BlockKind::FootnoteDefinition(label) => Event::End(Tag::FootnoteDefinition(label)),
+                BlockKind::HtmlBlock => {
+                    self.go_to_next_sibling();
+                    return self.next_event();
+                }
+                BlockKind::IndentedCodeBlock => Event::End(Tag::IndentedCodeBlock),
+                BlockKind::List { start, .. } => Event::End(Tag::List(start)),
+                BlockKind::ListItem(_) => Event::End(Tag::Item),
+                BlockKind::Table => Event::End(Tag::Table(self.table_alignments(index))),
+                BlockKind::TableHead => Event::End(Tag::TableHead),
+                BlockKind::TableRow => Event::End(Tag::TableRow),
+                _ => panic!("Unexpected node is found as a parent."),
+            };
+            self.go_to_next_sibling();
+            let range = block.begin..block.end + 1;
+            // Code block ends with line ending, even if its last line has none at the end of text.
+            if matches!(
+                block.kind,
+                BlockKind::FencedCodeBlock(_) | BlockKind::IndentedCodeBlock
+            ) && self.ends_without_line_ending(index)
+            {
+                self.events.push_back((event, range.clone()));
+                return Some((Event::Text(Cow::Borrowed("\n")), range.end..range.end));
             }
+            Some((event, range))
+        }
+    }
+}
+
+/// Parse heading attributes (e.g. `#id .class1 .class2`) into id and classes, where the last id wins.
+/// Other kinds of attributes are ignored.
+fn parse_heading_attributes(attributes: &str) -> (Option<&str>, Vec<&str>) {
+    let mut id = None;
+    let mut classes = Vec::new();
+    for attribute in attributes.split_ascii_whitespace() {
+        if let Some(value) = attribute.strip_prefix('#') {
+            id = Some(value);
+        } else if let Some(value) = attribute.strip_prefix('.') {
+            classes.push(value);
         }
     }
+    (id, classes)
 }
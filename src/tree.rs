@@ -1,15 +1,18 @@
+/// Arena of nodes, where each node is linked to its parent, first child, and previous and next siblings.
+///
+/// Positions for traversal are kept by `TreeCursor` or `NodeRef` outside of the tree,
+/// so that the tree can be traversed by any number of them independently.
+/// Tree is built by parsers in this crate, and read-only outside of it.
 #[derive(Debug)]
 pub struct Tree<T> {
-    pub ancestors: Vec<usize>,
-    pub current: Option<usize>,
-    pub nodes: Vec<Node<T>>,
+    first: Option<usize>,
+    nodes: Vec<Node<T>>,
 }
 
 impl<T> Default for Tree<T> {
     fn default() -> Self {
         Self {
-            ancestors: Vec::new(),
-            current: None,
+            first: None,
             nodes: Vec::new(),
         }
     }
@@ -20,55 +23,341 @@ impl<T> Tree<T> {
         Self::default()
     }
 
-    /// Append an item to the current position.
-    pub fn append(&mut self, item: T) {
-        let index = self.create_node(item);
-        let next_current = Some(index);
+    /// Return the number of nodes, including the ones removed from their parents.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
 
-        if let Some(index) = self.current {
-            // When some sibling already exists.
-            self.nodes[index].next = next_current;
-        } else if let Some(&parent) = self.ancestors.last() {
-            // When no sibling exists yet, but parent does.
-            self.nodes[parent].child = next_current;
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Return the node of given index, or `None` if out of bounds.
+    pub fn get(&self, index: usize) -> Option<NodeRef<'_, T>> {
+        if index < self.nodes.len() {
+            Some(NodeRef { tree: self, index })
+        } else {
+            None
         }
-        self.current = next_current;
     }
 
-    pub fn go_to_next_sibling(&mut self) {
-        let index = self.current.unwrap();
-        self.current = self.nodes[index].next;
+    /// Return the item of given index, or `None` if out of bounds.
+    pub fn item(&self, index: usize) -> Option<&T> {
+        self.nodes.get(index).map(|node| &node.item)
     }
 
-    pub fn go_to_parent(&mut self) {
-        self.current = self.ancestors.pop();
+    pub(crate) fn item_mut(&mut self, index: usize) -> Option<&mut T> {
+        self.nodes.get_mut(index).map(|node| &mut node.item)
     }
 
-    pub fn go_to_child(&mut self) {
-        let index = self.current.unwrap();
-        self.ancestors.push(index);
-        self.current = self.nodes[index].child;
+    /// Return the first top-level node.
+    pub fn first(&self) -> Option<NodeRef<'_, T>> {
+        self.first.map(|index| NodeRef { tree: self, index })
     }
 
-    pub fn go_to_first(&mut self) {
-        self.current = if self.nodes.is_empty() { None } else { Some(0) };
+    /// Return iterator of top-level nodes.
+    pub fn roots(&self) -> Siblings<'_, T> {
+        Siblings { next: self.first() }
+    }
+
+    /// Return iterator of all nodes in the tree in pre-order.
+    pub fn descendants(&self) -> Descendants<'_, T> {
+        Descendants {
+            root: None,
+            next: self.first(),
+        }
     }
 
-    /// Create a new Node, and return its index.
-    fn create_node(&mut self, item: T) -> usize {
+    /// Insert a node of given item as a child of given parent (or top-level node if `None`),
+    /// right after given previous sibling (or as the first child if `None`), and return its index.
+    pub(crate) fn insert(
+        &mut self,
+        parent: Option<usize>,
+        previous: Option<usize>,
+        item: T,
+    ) -> usize {
         let index = self.nodes.len();
+        let next = match previous {
+            Some(previous) => self.nodes[previous].next.replace(index),
+            None => match parent {
+                Some(parent) => self.nodes[parent].child.replace(index),
+                None => self.first.replace(index),
+            },
+        };
+        if let Some(next) = next {
+            self.nodes[next].previous = Some(index);
+        }
         self.nodes.push(Node {
             child: None,
             item,
-            next: None,
+            next,
+            parent,
+            previous,
         });
         index
     }
+
+    /// Remove the node of given index from its siblings with its descendants, and return its previous sibling.
+    /// The removed nodes are kept in the arena, but no longer reachable from the others.
+    pub(crate) fn remove(&mut self, index: usize) -> Option<usize> {
+        let parent = self.nodes[index].parent.take();
+        let previous = self.nodes[index].previous.take();
+        let next = self.nodes[index].next.take();
+        if let Some(next) = next {
+            self.nodes[next].previous = previous;
+        }
+        match (previous, parent) {
+            (Some(previous), _) => self.nodes[previous].next = next,
+            (None, Some(parent)) => self.nodes[parent].child = next,
+            (None, None) if self.first == Some(index) => self.first = next,
+            // Already removed.
+            (None, None) => {}
+        }
+        previous
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
-pub struct Node<T> {
-    pub child: Option<usize>,
-    pub next: Option<usize>,
-    pub item: T,
+struct Node<T> {
+    child: Option<usize>,
+    item: T,
+    next: Option<usize>,
+    parent: Option<usize>,
+    previous: Option<usize>,
+}
+
+/// Read-only reference to a node in tree, which can be used to query its relatives.
+pub struct NodeRef<'t, T> {
+    tree: &'t Tree<T>,
+    index: usize,
+}
+
+impl<'t, T> Clone for NodeRef<'t, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'t, T> Copy for NodeRef<'t, T> {}
+
+impl<'t, T> NodeRef<'t, T> {
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn item(&self) -> &'t T {
+        &self.tree.nodes[self.index].item
+    }
+
+    pub fn parent(&self) -> Option<Self> {
+        self.relative(self.node().parent)
+    }
+
+    pub fn first_child(&self) -> Option<Self> {
+        self.relative(self.node().child)
+    }
+
+    pub fn next_sibling(&self) -> Option<Self> {
+        self.relative(self.node().next)
+    }
+
+    pub fn previous_sibling(&self) -> Option<Self> {
+        self.relative(self.node().previous)
+    }
+
+    /// Return iterator of children in order.
+    pub fn children(&self) -> Siblings<'t, T> {
+        Siblings {
+            next: self.first_child(),
+        }
+    }
+
+    /// Return iterator of ancestors, from the parent to the top-level one.
+    pub fn ancestors(&self) -> Ancestors<'t, T> {
+        Ancestors {
+            next: self.parent(),
+        }
+    }
+
+    /// Return iterator of descendants in pre-order, excluding this node.
+    pub fn descendants(&self) -> Descendants<'t, T> {
+        Descendants {
+            root: Some(self.index),
+            next: self.first_child(),
+        }
+    }
+
+    fn node(&self) -> &'t Node<T> {
+        &self.tree.nodes[self.index]
+    }
+
+    fn relative(&self, index: Option<usize>) -> Option<Self> {
+        index.map(|index| Self {
+            tree: self.tree,
+            index,
+        })
+    }
+}
+
+/// Iterator of a node and its following siblings.
+pub struct Siblings<'t, T> {
+    next: Option<NodeRef<'t, T>>,
+}
+
+impl<'t, T> Iterator for Siblings<'t, T> {
+    type Item = NodeRef<'t, T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.next?;
+        self.next = node.next_sibling();
+        Some(node)
+    }
+}
+
+/// Iterator of ancestors of a node, from the innermost one.
+pub struct Ancestors<'t, T> {
+    next: Option<NodeRef<'t, T>>,
+}
+
+impl<'t, T> Iterator for Ancestors<'t, T> {
+    type Item = NodeRef<'t, T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.next?;
+        self.next = node.parent();
+        Some(node)
+    }
+}
+
+/// Iterator of descendants of a node (or all nodes in tree if `root` is `None`) in pre-order,
+/// which follows parent links instead of keeping a stack.
+pub struct Descendants<'t, T> {
+    root: Option<usize>,
+    next: Option<NodeRef<'t, T>>,
+}
+
+impl<'t, T> Iterator for Descendants<'t, T> {
+    type Item = NodeRef<'t, T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.next?;
+        self.next = node.first_child();
+        // Without children, the next one is the next sibling of the node or its nearest ancestor under the root.
+        let mut relative = node;
+        while self.next.is_none() && Some(relative.index) != self.root {
+            self.next = relative.next_sibling();
+            match relative.parent() {
+                Some(parent) => relative = parent,
+                None => break,
+            }
+        }
+        Some(node)
+    }
+}
+
+/// Position in tree, which consists of the current node and its ancestors.
+///
+/// The current node is `None` only if the position is before the first child of an empty parent when building tree.
+/// Moves to missing nodes fail without changing the position.
+#[derive(Clone, Debug, Default)]
+pub struct TreeCursor {
+    ancestors: Vec<usize>,
+    current: Option<usize>,
+}
+
+impl TreeCursor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn current(&self) -> Option<usize> {
+        self.current
+    }
+
+    /// Return indexes of ancestors of the current position, from the top-level one.
+    pub fn ancestors(&self) -> &[usize] {
+        &self.ancestors
+    }
+
+    pub fn parent(&self) -> Option<usize> {
+        self.ancestors.last().copied()
+    }
+
+    /// Go to the first top-level node of given tree.
+    pub fn go_to_first<T>(&mut self, tree: &Tree<T>) {
+        self.ancestors.clear();
+        self.current = tree.first;
+    }
+
+    /// Go to the first child of the current node, and return false if there is no such node.
+    pub fn go_to_child<T>(&mut self, tree: &Tree<T>) -> bool {
+        match self.current {
+            Some(index) => match tree.nodes.get(index).and_then(|node| node.child) {
+                Some(child) => {
+                    self.ancestors.push(index);
+                    self.current = Some(child);
+                    true
+                }
+                None => false,
+            },
+            None => false,
+        }
+    }
+
+    /// Go to the next sibling of the current node, and return false if there is no such node.
+    pub fn go_to_next_sibling<T>(&mut self, tree: &Tree<T>) -> bool {
+        match self.current.and_then(|index| tree.nodes.get(index)?.next) {
+            Some(next) => {
+                self.current = Some(next);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Go back to the parent, and return false if the position is top-level.
+    pub fn go_to_parent(&mut self) -> bool {
+        match self.ancestors.pop() {
+            Some(parent) => {
+                self.current = Some(parent);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Insert a node of given item right after the current position, and go to it.
+    pub(crate) fn append<T>(&mut self, tree: &mut Tree<T>, item: T) {
+        self.current = Some(tree.insert(self.parent(), self.current, item));
+    }
+
+    /// Insert a node of given item right after the current position as an open node, and go to its children.
+    pub(crate) fn open<T>(&mut self, tree: &mut Tree<T>, item: T) {
+        self.append(tree, item);
+        self.ancestors.extend(self.current.take());
+    }
+
+    /// Close the innermost open node, go back to it, and return its index.
+    pub(crate) fn close(&mut self) -> Option<usize> {
+        if self.go_to_parent() {
+            self.current
+        } else {
+            None
+        }
+    }
+
+    /// Remove the current node from given tree, and go to its previous sibling.
+    pub(crate) fn remove_current<T>(&mut self, tree: &mut Tree<T>) {
+        if let Some(index) = self.current {
+            self.remove(tree, index);
+        }
+    }
+
+    /// Remove the node of given index from given tree, and go to its previous sibling if it is the current node.
+    pub(crate) fn remove<T>(&mut self, tree: &mut Tree<T>, index: usize) {
+        let previous = tree.remove(index);
+        if self.current == Some(index) {
+            self.current = previous;
+        }
+    }
 }
@@ -0,0 +1,8 @@
+/// Column alignment of table, specified by colons in its delimiter row (e.g. `:-:`).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Alignment {
+    Center,
+    Left,
+    None,
+    Right,
+}
@@ -1,4 +1,4 @@
-use crate::types::HeadingLevel;
+use crate::types::{Alignment, HeadingLevel};
 
 #[derive(Clone, Copy, Debug)]
 pub struct Block<'a> {
@@ -11,9 +11,24 @@ pub struct Block<'a> {
 pub enum BlockKind<'a> {
     BlockQuote,
     FencedCodeBlock(&'a str),
-    Heading(HeadingLevel),
+    FootnoteDefinition(&'a str),
+    /// Heading of given level, with its raw attributes (e.g. `#id .class`) if any.
+    Heading(HeadingLevel, Option<&'a str>),
     Html,
+    HtmlBlock,
     IndentedCodeBlock,
+    List {
+        marker: u8,
+        start: Option<u64>,
+        tight: bool,
+    },
+    ListItem(usize),
+    Spaces(usize),
+    Table,
+    TableCell(Alignment),
+    TableHead,
+    TableRow,
+    TaskListMarker(bool),
     Paragraph,
     Text,
     ThematicBreak,
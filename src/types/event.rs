@@ -1,10 +1,17 @@
 use crate::types::Tag;
+use std::borrow::Cow;
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Event<'a> {
     Begin(Tag<'a>),
+    Code(Cow<'a, str>),
     End(Tag<'a>),
+    FootnoteReference(&'a str),
+    HardBreak,
     Html(&'a str),
-    Text(&'a str),
+    InlineHtml(&'a str),
+    SoftBreak,
+    TaskListMarker(bool),
+    Text(Cow<'a, str>),
     ThematicBreak,
 }
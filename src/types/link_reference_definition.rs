@@ -0,0 +1,7 @@
+use std::borrow::Cow;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LinkReferenceDefinition<'a> {
+    pub dest: Cow<'a, str>,
+    pub title: Cow<'a, str>,
+}
@@ -1,9 +1,15 @@
+mod alignment;
 mod block;
 mod event;
 mod heading_level;
+mod link_reference_definition;
+mod options;
 mod tag;
 
+pub use alignment::*;
 pub use block::*;
 pub use event::*;
 pub use heading_level::*;
+pub use link_reference_definition::*;
+pub use options::*;
 pub use tag::*;
@@ -0,0 +1,140 @@
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, Sub, SubAssign};
+
+/// Set of flags to enable extensions to CommonMark, which are all disabled by default.
+///
+/// Some flags (`ENABLE_TAGFILTER`, `ENABLE_FOOTNOTE_SECTION`, and `ENABLE_SOURCEPOS`) only change how HTML is rendered,
+/// and are ignored by `Parser::new_ext`. They share the flag space with the others
+/// so that the same options can be passed to both the parser and `html::push_html_ext` and its variants.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Options(u32);
+
+impl Options {
+    /// Enable GitHub Flavored Markdown tables.
+    pub const ENABLE_TABLES: Self = Self(1 << 0);
+
+    /// Enable GitHub Flavored Markdown strikethrough (e.g. `~~text~~`).
+    pub const ENABLE_STRIKETHROUGH: Self = Self(1 << 1);
+
+    /// Enable GitHub Flavored Markdown task list items (e.g. `- [x] done`).
+    pub const ENABLE_TASKLISTS: Self = Self(1 << 2);
+
+    /// Enable GitHub Flavored Markdown extended autolinks (e.g. `www.example.com`) without angle brackets.
+    pub const ENABLE_EXTENDED_AUTOLINKS: Self = Self(1 << 3);
+
+    /// Enable GitHub Flavored Markdown tagfilter on raw HTML in `html::push_html_ext`,
+    /// which disables some tags (e.g. `<script>`) by escaping their leading `<`. Renderer-only.
+    pub const ENABLE_TAGFILTER: Self = Self(1 << 4);
+
+    /// Enable footnote references (e.g. `[^1]`) and footnote definitions (e.g. `[^1]: text`).
+    pub const ENABLE_FOOTNOTES: Self = Self(1 << 5);
+
+    /// Collect footnote definitions into a numbered section at the end in `html::push_html_ext`,
+    /// instead of rendering them in place. Renderer-only.
+    pub const ENABLE_FOOTNOTE_SECTION: Self = Self(1 << 6);
+
+    /// Enable heading attributes (e.g. `# Heading {#id .class}`) at the end of headings.
+    pub const ENABLE_HEADING_ATTRIBUTES: Self = Self(1 << 7);
+
+    /// Enable smart punctuation, which turns quotes into curly ones, `--` and `---` into dashes, and `...` into ellipsis.
+    pub const ENABLE_SMART_PUNCTUATION: Self = Self(1 << 8);
+
+    /// Add `data-sourcepos` attributes (e.g. `data-sourcepos="1:1-2:5"`) to block elements in `html::push_html_offsets`,
+    /// which tell their line and column ranges in source text. Renderer-only.
+    pub const ENABLE_SOURCEPOS: Self = Self(1 << 9);
+
+    /// Return options with no extension enabled, which means strict CommonMark.
+    pub fn empty() -> Self {
+        Self(0)
+    }
+
+    /// Return options with all extensions enabled.
+    pub fn all() -> Self {
+        Self::ENABLE_TABLES
+            | Self::ENABLE_STRIKETHROUGH
+            | Self::ENABLE_TASKLISTS
+            | Self::ENABLE_EXTENDED_AUTOLINKS
+            | Self::ENABLE_TAGFILTER
+            | Self::ENABLE_FOOTNOTES
+            | Self::ENABLE_FOOTNOTE_SECTION
+            | Self::ENABLE_HEADING_ATTRIBUTES
+            | Self::ENABLE_SMART_PUNCTUATION
+            | Self::ENABLE_SOURCEPOS
+    }
+
+    /// Check if no extension is enabled.
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Check if all of the flags in given options are enabled.
+    pub fn contains(&self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Enable the flags in given options.
+    pub fn insert(&mut self, other: Self) {
+        self.0 |= other.0;
+    }
+
+    /// Disable the flags in given options.
+    pub fn remove(&mut self, other: Self) {
+        self.0 &= !other.0;
+    }
+
+    /// Return options with the flags enabled in either of them.
+    pub fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    /// Return options with the flags enabled in both of them.
+    pub fn intersection(self, other: Self) -> Self {
+        Self(self.0 & other.0)
+    }
+
+    /// Return options with the flags enabled in this one but not in given one.
+    pub fn difference(self, other: Self) -> Self {
+        Self(self.0 & !other.0)
+    }
+}
+
+impl BitOr for Options {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        self.union(other)
+    }
+}
+
+impl BitOrAssign for Options {
+    fn bitor_assign(&mut self, other: Self) {
+        self.insert(other);
+    }
+}
+
+impl BitAnd for Options {
+    type Output = Self;
+
+    fn bitand(self, other: Self) -> Self {
+        self.intersection(other)
+    }
+}
+
+impl BitAndAssign for Options {
+    fn bitand_assign(&mut self, other: Self) {
+        *self = self.intersection(other);
+    }
+}
+
+impl Sub for Options {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        self.difference(other)
+    }
+}
+
+impl SubAssign for Options {
+    fn sub_assign(&mut self, other: Self) {
+        self.remove(other);
+    }
+}
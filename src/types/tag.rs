@@ -1,10 +1,33 @@
-use crate::types::HeadingLevel;
+use crate::types::{Alignment, HeadingLevel};
+use std::borrow::Cow;
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Tag<'a> {
     BlockQuote,
-    FencedCodeBlock(&'a str),
-    Heading(HeadingLevel),
+    Emphasis,
+    FencedCodeBlock(Cow<'a, str>),
+    FootnoteDefinition(&'a str),
+    Heading {
+        level: HeadingLevel,
+        id: Option<&'a str>,
+        classes: Vec<&'a str>,
+    },
+    Image {
+        dest: Cow<'a, str>,
+        title: Cow<'a, str>,
+    },
     IndentedCodeBlock,
+    Item,
+    Link {
+        dest: Cow<'a, str>,
+        title: Cow<'a, str>,
+    },
+    List(Option<u64>),
     Paragraph,
+    Strikethrough,
+    Strong,
+    Table(Vec<Alignment>),
+    TableCell,
+    TableHead,
+    TableRow,
 }
@@ -0,0 +1,548 @@
+use crate::ast::{Node, NodeKind};
+
+/// Hooks to visit each node of document tree in order.
+///
+/// `visit_node` calls `enter` (pre-order), the method for the kind of node, and `leave` (post-order).
+/// The methods for each kind visit the children by default, so overriding ones should call `walk_children`
+/// unless they intend to skip the children.
+///
+/// ```
+/// use markus::ast::{Node, NodeKind};
+/// use markus::parser::Parser;
+/// use markus::visit::Visitor;
+///
+/// #[derive(Default)]
+/// struct CodeBlockCollector(Vec<String>);
+///
+/// impl Visitor for CodeBlockCollector {
+///     fn visit_code_block(&mut self, node: &Node) {
+///         if let Some(Node { kind: NodeKind::Text(value), .. }) = node.children().first() {
+///             self.0.push(value.clone());
+///         }
+///     }
+/// }
+///
+/// let document = Node::from_events(Parser::new("```\na\n```\n\n> ```\n> b\n> ```\n"));
+/// let mut collector = CodeBlockCollector::default();
+/// collector.visit_node(&document);
+/// assert_eq!(collector.0, vec!["a\n", "b\n"]);
+/// ```
+pub trait Visitor {
+    /// Called with every node before its children.
+    fn enter(&mut self, _node: &Node) {}
+
+    /// Called with every node after its children.
+    fn leave(&mut self, _node: &Node) {}
+
+    fn visit_node(&mut self, node: &Node) {
+        walk_node(self, node);
+    }
+
+    fn visit_block_quote(&mut self, node: &Node) {
+        walk_children(self, node);
+    }
+
+    fn visit_code_block(&mut self, node: &Node) {
+        walk_children(self, node);
+    }
+
+    fn visit_document(&mut self, node: &Node) {
+        walk_children(self, node);
+    }
+
+    fn visit_emphasis(&mut self, node: &Node) {
+        walk_children(self, node);
+    }
+
+    fn visit_footnote_definition(&mut self, node: &Node) {
+        walk_children(self, node);
+    }
+
+    fn visit_heading(&mut self, node: &Node) {
+        walk_children(self, node);
+    }
+
+    fn visit_image(&mut self, node: &Node) {
+        walk_children(self, node);
+    }
+
+    fn visit_item(&mut self, node: &Node) {
+        walk_children(self, node);
+    }
+
+    fn visit_link(&mut self, node: &Node) {
+        walk_children(self, node);
+    }
+
+    fn visit_list(&mut self, node: &Node) {
+        walk_children(self, node);
+    }
+
+    fn visit_paragraph(&mut self, node: &Node) {
+        walk_children(self, node);
+    }
+
+    fn visit_strikethrough(&mut self, node: &Node) {
+        walk_children(self, node);
+    }
+
+    fn visit_strong(&mut self, node: &Node) {
+        walk_children(self, node);
+    }
+
+    fn visit_table(&mut self, node: &Node) {
+        walk_children(self, node);
+    }
+
+    fn visit_table_cell(&mut self, node: &Node) {
+        walk_children(self, node);
+    }
+
+    fn visit_table_head(&mut self, node: &Node) {
+        walk_children(self, node);
+    }
+
+    fn visit_table_row(&mut self, node: &Node) {
+        walk_children(self, node);
+    }
+
+    fn visit_code(&mut self, _node: &Node) {}
+
+    fn visit_footnote_reference(&mut self, _node: &Node) {}
+
+    fn visit_hard_break(&mut self, _node: &Node) {}
+
+    fn visit_html(&mut self, _node: &Node) {}
+
+    fn visit_inline_html(&mut self, _node: &Node) {}
+
+    fn visit_soft_break(&mut self, _node: &Node) {}
+
+    fn visit_task_list_marker(&mut self, _node: &Node) {}
+
+    fn visit_text(&mut self, _node: &Node) {}
+
+    fn visit_thematic_break(&mut self, _node: &Node) {}
+}
+
+/// Visit given node by `enter`, the method for its kind, and `leave`.
+pub fn walk_node<V>(visitor: &mut V, node: &Node)
+where
+    V: Visitor + ?Sized,
+{
+    visitor.enter(node);
+    match node.kind {
+        NodeKind::BlockQuote => visitor.visit_block_quote(node),
+        NodeKind::CodeBlock(_) => visitor.visit_code_block(node),
+        NodeKind::Document => visitor.visit_document(node),
+        NodeKind::Emphasis => visitor.visit_emphasis(node),
+        NodeKind::FootnoteDefinition(_) => visitor.visit_footnote_definition(node),
+        NodeKind::Heading { .. } => visitor.visit_heading(node),
+        NodeKind::Image { .. } => visitor.visit_image(node),
+        NodeKind::Item => visitor.visit_item(node),
+        NodeKind::Link { .. } => visitor.visit_link(node),
+        NodeKind::List(_) => visitor.visit_list(node),
+        NodeKind::Paragraph => visitor.visit_paragraph(node),
+        NodeKind::Strikethrough => visitor.visit_strikethrough(node),
+        NodeKind::Strong => visitor.visit_strong(node),
+        NodeKind::Table(_) => visitor.visit_table(node),
+        NodeKind::TableCell => visitor.visit_table_cell(node),
+        NodeKind::TableHead => visitor.visit_table_head(node),
+        NodeKind::TableRow => visitor.visit_table_row(node),
+        NodeKind::Code(_) => visitor.visit_code(node),
+        NodeKind::FootnoteReference(_) => visitor.visit_footnote_reference(node),
+        NodeKind::HardBreak => visitor.visit_hard_break(node),
+        NodeKind::Html(_) => visitor.visit_html(node),
+        NodeKind::InlineHtml(_) => visitor.visit_inline_html(node),
+        NodeKind::SoftBreak => visitor.visit_soft_break(node),
+        NodeKind::TaskListMarker(_) => visitor.visit_task_list_marker(node),
+        NodeKind::Text(_) => visitor.visit_text(node),
+        NodeKind::ThematicBreak => visitor.visit_thematic_break(node),
+    }
+    visitor.leave(node);
+}
+
+/// Visit children of given node in order.
+pub fn walk_children<V>(visitor: &mut V, node: &Node)
+where
+    V: Visitor + ?Sized,
+{
+    for child in node.children() {
+        visitor.visit_node(child);
+    }
+}
+
+/// Hooks to edit each node of document tree in place, in the same order as `Visitor`.
+///
+/// ```
+/// use markus::ast::{Node, NodeKind};
+/// use markus::html::push_html;
+/// use markus::parser::Parser;
+/// use markus::types::HeadingLevel;
+/// use markus::visit::{walk_children_mut, VisitorMut};
+/// use std::convert::TryFrom;
+///
+/// struct HeadingDemoter;
+///
+/// impl VisitorMut for HeadingDemoter {
+///     fn visit_heading(&mut self, node: &mut Node) {
+///         if let NodeKind::Heading { level, .. } = &mut node.kind {
+///             *level = HeadingLevel::try_from(*level as usize + 1).unwrap_or(HeadingLevel::H6);
+///         }
+///         walk_children_mut(self, node);
+///     }
+/// }
+///
+/// let mut document = Node::from_events(Parser::new("# a\n\n###### b\n"));
+/// HeadingDemoter.visit_node(&mut document);
+/// let mut buffer = String::new();
+/// push_html(&mut buffer, document.events());
+/// assert_eq!(buffer, "<h2>a</h2>\n<h6>b</h6>\n");
+/// ```
+pub trait VisitorMut {
+    /// Called with every node before its children.
+    fn enter(&mut self, _node: &mut Node) {}
+
+    /// Called with every node after its children.
+    fn leave(&mut self, _node: &mut Node) {}
+
+    fn visit_node(&mut self, node: &mut Node) {
+        walk_node_mut(self, node);
+    }
+
+    fn visit_block_quote(&mut self, node: &mut Node) {
+        walk_children_mut(self, node);
+    }
+
+    fn visit_code_block(&mut self, node: &mut Node) {
+        walk_children_mut(self, node);
+    }
+
+    fn visit_document(&mut self, node: &mut Node) {
+        walk_children_mut(self, node);
+    }
+
+    fn visit_emphasis(&mut self, node: &mut Node) {
+        walk_children_mut(self, node);
+    }
+
+    fn visit_footnote_definition(&mut self, node: &mut Node) {
+        walk_children_mut(self, node);
+    }
+
+    fn visit_heading(&mut self, node: &mut Node) {
+        walk_children_mut(self, node);
+    }
+
+    fn visit_image(&mut self, node: &mut Node) {
+        walk_children_mut(self, node);
+    }
+
+    fn visit_item(&mut self, node: &mut Node) {
+        walk_children_mut(self, node);
+    }
+
+    fn visit_link(&mut self, node: &mut Node) {
+        walk_children_mut(self, node);
+    }
+
+    fn visit_list(&mut self, node: &mut Node) {
+        walk_children_mut(self, node);
+    }
+
+    fn visit_paragraph(&mut self, node: &mut Node) {
+        walk_children_mut(self, node);
+    }
+
+    fn visit_strikethrough(&mut self, node: &mut Node) {
+        walk_children_mut(self, node);
+    }
+
+    fn visit_strong(&mut self, node: &mut Node) {
+        walk_children_mut(self, node);
+    }
+
+    fn visit_table(&mut self, node: &mut Node) {
+        walk_children_mut(self, node);
+    }
+
+    fn visit_table_cell(&mut self, node: &mut Node) {
+        walk_children_mut(self, node);
+    }
+
+    fn visit_table_head(&mut self, node: &mut Node) {
+        walk_children_mut(self, node);
+    }
+
+    fn visit_table_row(&mut self, node: &mut Node) {
+        walk_children_mut(self, node);
+    }
+
+    fn visit_code(&mut self, _node: &mut Node) {}
+
+    fn visit_footnote_reference(&mut self, _node: &mut Node) {}
+
+    fn visit_hard_break(&mut self, _node: &mut Node) {}
+
+    fn visit_html(&mut self, _node: &mut Node) {}
+
+    fn visit_inline_html(&mut self, _node: &mut Node) {}
+
+    fn visit_soft_break(&mut self, _node: &mut Node) {}
+
+    fn visit_task_list_marker(&mut self, _node: &mut Node) {}
+
+    fn visit_text(&mut self, _node: &mut Node) {}
+
+    fn visit_thematic_break(&mut self, _node: &mut Node) {}
+}
+
+/// Visit given node by `enter`, the method for its kind, and `leave`.
+/// The method is chosen by the kind after `enter`, so that `enter` can change it.
+pub fn walk_node_mut<V>(visitor: &mut V, node: &mut Node)
+where
+    V: VisitorMut + ?Sized,
+{
+    visitor.enter(node);
+    match node.kind {
+        NodeKind::BlockQuote => visitor.visit_block_quote(node),
+        NodeKind::CodeBlock(_) => visitor.visit_code_block(node),
+        NodeKind::Document => visitor.visit_document(node),
+        NodeKind::Emphasis => visitor.visit_emphasis(node),
+        NodeKind::FootnoteDefinition(_) => visitor.visit_footnote_definition(node),
+        NodeKind::Heading { .. } => visitor.visit_heading(node),
+        NodeKind::Image { .. } => visitor.visit_image(node),
+        NodeKind::Item => visitor.visit_item(node),
+        NodeKind::Link { .. } => visitor.visit_link(node),
+        NodeKind::List(_) => visitor.visit_list(node),
+        NodeKind::Paragraph => visitor.visit_paragraph(node),
+        NodeKind::Strikethrough => visitor.visit_strikethrough(node),
+        NodeKind::Strong => visitor.visit_strong(node),
+        NodeKind::Table(_) => visitor.visit_table(node),
+        NodeKind::TableCell => visitor.visit_table_cell(node),
+        NodeKind::TableHead => visitor.visit_table_head(node),
+        NodeKind::TableRow => visitor.visit_table_row(node),
+        NodeKind::Code(_) => visitor.visit_code(node),
+        NodeKind::FootnoteReference(_) => visitor.visit_footnote_reference(node),
+        NodeKind::HardBreak => visitor.visit_hard_break(node),
+        NodeKind::Html(_) => visitor.visit_html(node),
+        NodeKind::InlineHtml(_) => visitor.visit_inline_html(node),
+        NodeKind::SoftBreak => visitor.visit_soft_break(node),
+        NodeKind::TaskListMarker(_) => visitor.visit_task_list_marker(node),
+        NodeKind::Text(_) => visitor.visit_text(node),
+        NodeKind::ThematicBreak => visitor.visit_thematic_break(node),
+    }
+    visitor.leave(node);
+}
+
+/// Visit children of given node in order.
+pub fn walk_children_mut<V>(visitor: &mut V, node: &mut Node)
+where
+    V: VisitorMut + ?Sized,
+{
+    for child in node.children_mut() {
+        visitor.visit_node(child);
+    }
+}
+
+/// Hooks to rebuild document tree by taking each node and returning nodes to replace it,
+/// which can also remove or split nodes unlike `VisitorMut`.
+///
+/// `fold_node` calls `fold_enter`, the method for the kind of node, and `fold_leave` on each returned node.
+/// The methods for each kind fold the children by default, so overriding ones should call `fold_children`
+/// unless they intend to keep the children as they are.
+///
+/// ```
+/// use markus::ast::Node;
+/// use markus::html::push_html;
+/// use markus::parser::Parser;
+/// use markus::visit::{fold_children, Fold};
+///
+/// // Replace emphasis with its children.
+/// struct EmphasisRemover;
+///
+/// impl Fold for EmphasisRemover {
+///     fn fold_emphasis(&mut self, node: Node) -> Vec<Node> {
+///         fold_children(self, node).take_children()
+///     }
+/// }
+///
+/// let document = fold_children(&mut EmphasisRemover, Node::from_events(Parser::new("*a* **b**\n")));
+/// let mut buffer = String::new();
+/// push_html(&mut buffer, document.events());
+/// assert_eq!(buffer, "<p>a <strong>b</strong></p>\n");
+/// ```
+pub trait Fold {
+    /// Called with every node before the method for its kind.
+    fn fold_enter(&mut self, node: Node) -> Node {
+        node
+    }
+
+    /// Called with every node returned by the method for its kind, and return nodes to replace it
+    /// (e.g. empty to remove it).
+    fn fold_leave(&mut self, node: Node) -> Vec<Node> {
+        vec![node]
+    }
+
+    fn fold_node(&mut self, node: Node) -> Vec<Node> {
+        fold_node(self, node)
+    }
+
+    fn fold_block_quote(&mut self, node: Node) -> Vec<Node> {
+        vec![fold_children(self, node)]
+    }
+
+    fn fold_code_block(&mut self, node: Node) -> Vec<Node> {
+        vec![fold_children(self, node)]
+    }
+
+    fn fold_document(&mut self, node: Node) -> Vec<Node> {
+        vec![fold_children(self, node)]
+    }
+
+    fn fold_emphasis(&mut self, node: Node) -> Vec<Node> {
+        vec![fold_children(self, node)]
+    }
+
+    fn fold_footnote_definition(&mut self, node: Node) -> Vec<Node> {
+        vec![fold_children(self, node)]
+    }
+
+    fn fold_heading(&mut self, node: Node) -> Vec<Node> {
+        vec![fold_children(self, node)]
+    }
+
+    fn fold_image(&mut self, node: Node) -> Vec<Node> {
+        vec![fold_children(self, node)]
+    }
+
+    fn fold_item(&mut self, node: Node) -> Vec<Node> {
+        vec![fold_children(self, node)]
+    }
+
+    fn fold_link(&mut self, node: Node) -> Vec<Node> {
+        vec![fold_children(self, node)]
+    }
+
+    fn fold_list(&mut self, node: Node) -> Vec<Node> {
+        vec![fold_children(self, node)]
+    }
+
+    fn fold_paragraph(&mut self, node: Node) -> Vec<Node> {
+        vec![fold_children(self, node)]
+    }
+
+    fn fold_strikethrough(&mut self, node: Node) -> Vec<Node> {
+        vec![fold_children(self, node)]
+    }
+
+    fn fold_strong(&mut self, node: Node) -> Vec<Node> {
+        vec![fold_children(self, node)]
+    }
+
+    fn fold_table(&mut self, node: Node) -> Vec<Node> {
+        vec![fold_children(self, node)]
+    }
+
+    fn fold_table_cell(&mut self, node: Node) -> Vec<Node> {
+        vec![fold_children(self, node)]
+    }
+
+    fn fold_table_head(&mut self, node: Node) -> Vec<Node> {
+        vec![fold_children(self, node)]
+    }
+
+    fn fold_table_row(&mut self, node: Node) -> Vec<Node> {
+        vec![fold_children(self, node)]
+    }
+
+    fn fold_code(&mut self, node: Node) -> Vec<Node> {
+        vec![node]
+    }
+
+    fn fold_footnote_reference(&mut self, node: Node) -> Vec<Node> {
+        vec![node]
+    }
+
+    fn fold_hard_break(&mut self, node: Node) -> Vec<Node> {
+        vec![node]
+    }
+
+    fn fold_html(&mut self, node: Node) -> Vec<Node> {
+        vec![node]
+    }
+
+    fn fold_inline_html(&mut self, node: Node) -> Vec<Node> {
+        vec![node]
+    }
+
+    fn fold_soft_break(&mut self, node: Node) -> Vec<Node> {
+        vec![node]
+    }
+
+    fn fold_task_list_marker(&mut self, node: Node) -> Vec<Node> {
+        vec![node]
+    }
+
+    fn fold_text(&mut self, node: Node) -> Vec<Node> {
+        vec![node]
+    }
+
+    fn fold_thematic_break(&mut self, node: Node) -> Vec<Node> {
+        vec![node]
+    }
+}
+
+/// Fold given node by `fold_enter`, the method for its kind, and `fold_leave`.
+/// The method is chosen by the kind after `fold_enter`, so that `fold_enter` can change it.
+pub fn fold_node<F>(folder: &mut F, node: Node) -> Vec<Node>
+where
+    F: Fold + ?Sized,
+{
+    let node = folder.fold_enter(node);
+    let nodes = match node.kind {
+        NodeKind::BlockQuote => folder.fold_block_quote(node),
+        NodeKind::CodeBlock(_) => folder.fold_code_block(node),
+        NodeKind::Document => folder.fold_document(node),
+        NodeKind::Emphasis => folder.fold_emphasis(node),
+        NodeKind::FootnoteDefinition(_) => folder.fold_footnote_definition(node),
+        NodeKind::Heading { .. } => folder.fold_heading(node),
+        NodeKind::Image { .. } => folder.fold_image(node),
+        NodeKind::Item => folder.fold_item(node),
+        NodeKind::Link { .. } => folder.fold_link(node),
+        NodeKind::List(_) => folder.fold_list(node),
+        NodeKind::Paragraph => folder.fold_paragraph(node),
+        NodeKind::Strikethrough => folder.fold_strikethrough(node),
+        NodeKind::Strong => folder.fold_strong(node),
+        NodeKind::Table(_) => folder.fold_table(node),
+        NodeKind::TableCell => folder.fold_table_cell(node),
+        NodeKind::TableHead => folder.fold_table_head(node),
+        NodeKind::TableRow => folder.fold_table_row(node),
+        NodeKind::Code(_) => folder.fold_code(node),
+        NodeKind::FootnoteReference(_) => folder.fold_footnote_reference(node),
+        NodeKind::HardBreak => folder.fold_hard_break(node),
+        NodeKind::Html(_) => folder.fold_html(node),
+        NodeKind::InlineHtml(_) => folder.fold_inline_html(node),
+        NodeKind::SoftBreak => folder.fold_soft_break(node),
+        NodeKind::TaskListMarker(_) => folder.fold_task_list_marker(node),
+        NodeKind::Text(_) => folder.fold_text(node),
+        NodeKind::ThematicBreak => folder.fold_thematic_break(node),
+    };
+    nodes
+        .into_iter()
+        .flat_map(|node| folder.fold_leave(node))
+        .collect()
+}
+
+/// Fold children of given node in order, and return the node with the folded children
+/// (e.g. to fold document without replacing itself).
+pub fn fold_children<F>(folder: &mut F, mut node: Node) -> Node
+where
+    F: Fold + ?Sized,
+{
+    let mut children = Vec::new();
+    for child in node.take_children() {
+        children.extend(folder.fold_node(child));
+    }
+    Node::with_children(node.kind, children)
+}